@@ -3,28 +3,52 @@ extern crate cc;
 extern crate protoc_rust;
 
 use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
 fn main() {
     build_proto_file();
-    build_route_c();
+    let out_path = PathBuf::from(env::var("OUT_DIR").unwrap());
+    // the directory of the built binaries
+    let output_dir = out_path
+        .parent()
+        .unwrap()
+        .parent()
+        .unwrap()
+        .parent()
+        .unwrap();
+    if env::var_os("CARGO_FEATURE_NATIVE_ENGINE").is_some() {
+        build_route_c(&out_path, output_dir);
+    }
+    copy_runtime_files(output_dir);
+}
+
+/// File name of the routing library on the target platform
+fn route_library_file() -> &'static str {
+    match env::var("CARGO_CFG_TARGET_OS").unwrap_or_default().as_str() {
+        "macos" | "ios" => "librout_server.dylib",
+        "windows" => "rout_server.dll",
+        _ => "librout_server.so",
+    }
 }
 
-fn build_route_c() {
-    cc::Build::new()
+fn build_route_c(out_path: &Path, output_dir: &Path) {
+    let mut build = cc::Build::new();
+    build
         .file("src/addon/api_server/route_c/library-bridge.cpp")
         .cpp(true)
-        .cpp_link_stdlib("stdc++")
-        .cpp_set_stdlib("c++")
-        .flag("-std=c++11")
-        .compile("route");
+        .flag("-std=c++11");
+    if "macos" == env::var("CARGO_CFG_TARGET_OS").unwrap_or_default() {
+        build.cpp_link_stdlib("c++").cpp_set_stdlib("c++");
+    } else {
+        build.cpp_link_stdlib("stdc++");
+    }
+    build.compile("route");
     println!("cargo:rerun-if-changed=src/addon/api_server/route_c/library-bridge.hpp");
     println!("cargo:rerun-if-changed=src/addon/api_server/route_c/library-bridge.cpp");
     // println!("cargo:rerun-if-changed=src/c/lib/routing_server.h");
     println!("cargo:rustc-link-search=src/addon/api_server/route_c");
     println!("cargo:rustc-link-lib=dylib=rout_server");
-    let out_path = PathBuf::from(env::var("OUT_DIR").unwrap());
     bindgen::Builder::default()
         .header("src/addon/api_server/route_c/library-bridge.hpp")
         .parse_callbacks(Box::new(bindgen::CargoCallbacks))
@@ -32,54 +56,32 @@ fn build_route_c() {
         .expect("Unable to generate bindings")
         .write_to_file(out_path.join("route.rs"))
         .expect("Couldn't write bindings!");
-    let to = out_path
-        .parent()
-        .unwrap()
-        .parent()
-        .unwrap()
-        .parent()
-        .unwrap()
-        .join("librout_server.dylib");
-    let output_dir = out_path
-        .parent()
-        .unwrap()
-        .parent()
-        .unwrap()
-        .parent()
-        .unwrap();
-    let source_library_file = "src/addon/api_server/route_c/librout_server.dylib";
-    let dest_library_file =
-        String::from(output_dir.as_os_str().to_str().expect("dest_library_file"))
-            + "/librout_server.dylib";
-    let source_config_file = "config.yaml";
-    let dest_config_file =
-        String::from(output_dir.as_os_str().to_str().expect("dest_config_file")) + "/config.yaml";
-    println!(
-        "{:?}:{:?}:{:?}:{:?}:{:?}",
-        output_dir, source_library_file, dest_library_file, source_config_file, dest_config_file
-    );
-    let ret = std::fs::copy(source_library_file, dest_library_file);
-    match ret {
-        Ok(_) => (),
-        Err(_) => panic!("error"),
+
+    let library_file = route_library_file();
+    let source_library_file = Path::new("src/addon/api_server/route_c").join(library_file);
+    println!("cargo:rerun-if-changed={}", source_library_file.display());
+    if let Err(error) = std::fs::copy(&source_library_file, output_dir.join(library_file)) {
+        println!(
+            "cargo:warning={} not copied: {}, the native engine needs it at run time",
+            source_library_file.display(),
+            error
+        );
     }
-    let ret = std::fs::copy(source_config_file, dest_config_file);
-    match ret {
-        Ok(_) => (),
-        Err(_) => panic!("error"),
+}
+
+/// Copies the server configuration and routing library data next to the
+/// binaries
+fn copy_runtime_files(output_dir: &Path) {
+    if let Err(error) = std::fs::copy("config.yaml", output_dir.join("config.yaml")) {
+        panic!("config.yaml not copied: {}", error);
     }
-    let dest_config_dir =
-        String::from(output_dir.as_os_str().to_str().expect("dest_config_dir"));
-    let dest_config_dir_tmp = "routinglib";
-    let mut params = String::from("cp -Rf ");
-    params += &dest_config_dir_tmp;
-    params += " ";
+    let mut params = String::from("cp -Rf routinglib ");
     params += output_dir.as_os_str().to_str().expect("out_dir");
     Command::new("sh")
-    .arg("-c")
-    .arg(params)
-    .output()
-    .expect("copy files is failed.");
+        .arg("-c")
+        .arg(params)
+        .output()
+        .expect("copy files is failed.");
 }
 
 fn build_proto_file() {
@@ -0,0 +1,120 @@
+use http::header::CONTENT_LENGTH;
+use hyper::body::{Bytes, HttpBody};
+use hyper::{Body, Request};
+use std::error::Error;
+use std::fmt::Display;
+
+#[derive(Debug)]
+pub enum BodyError {
+  /// The body is bigger than the configured limit
+  TooLarge(usize),
+  /// The body stream failed or its content is not valid
+  Malformed(String),
+}
+
+impl Error for BodyError {}
+
+impl Display for BodyError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      BodyError::TooLarge(limit) => write!(f, "body exceeds the limit of {} bytes", limit),
+      BodyError::Malformed(reason) => write!(f, "malformed body: {}", reason),
+    }
+  }
+}
+
+/// Collects every chunk of the `request` body into a single buffer.
+///
+/// Requests announcing a `Content-Length` bigger than `limit` are rejected
+/// before reading the body, otherwise the body is read until the
+/// accumulated chunks exceed `limit`.
+pub async fn aggregate(request: &mut Request<Body>, limit: usize) -> Result<Bytes, BodyError> {
+  if let Some(content_length) = request.headers().get(CONTENT_LENGTH) {
+    let content_length = content_length
+      .to_str()
+      .ok()
+      .and_then(|value| value.parse::<usize>().ok())
+      .ok_or_else(|| BodyError::Malformed(String::from("invalid Content-Length")))?;
+
+    if content_length > limit {
+      return Err(BodyError::TooLarge(limit));
+    }
+  }
+
+  let body = request.body_mut();
+  let mut buffer = Vec::<u8>::new();
+
+  while let Some(chunk) = body.data().await {
+    let chunk = chunk.map_err(|error| BodyError::Malformed(error.to_string()))?;
+
+    if buffer.len() + chunk.len() > limit {
+      return Err(BodyError::TooLarge(limit));
+    }
+
+    buffer.extend_from_slice(&chunk);
+  }
+
+  Ok(Bytes::from(buffer))
+}
+
+/// Collects the `request` body as an UTF-8 string
+pub async fn aggregate_string(
+  request: &mut Request<Body>,
+  limit: usize,
+) -> Result<String, BodyError> {
+  let bytes = aggregate(request, limit).await?;
+
+  String::from_utf8(bytes.to_vec()).map_err(|error| BodyError::Malformed(error.to_string()))
+}
+
+#[cfg(test)]
+pub mod tests {
+  use super::*;
+
+  fn make_chunked_request(chunks: Vec<&'static str>) -> Request<Body> {
+    let stream = futures::stream::iter(
+      chunks
+        .into_iter()
+        .map(|chunk| Ok::<_, std::io::Error>(Bytes::from(chunk))),
+    );
+
+    Request::builder().body(Body::wrap_stream(stream)).unwrap()
+  }
+
+  #[tokio::test]
+  async fn aggregates_every_chunk() {
+    let mut request = make_chunked_request(vec!["{\"version\":", " 1, ", "\"mode\": 0}"]);
+    let body = aggregate_string(&mut request, 1024).await.unwrap();
+
+    assert_eq!(body, "{\"version\": 1, \"mode\": 0}");
+  }
+
+  #[tokio::test]
+  async fn rejects_bodies_over_the_limit() {
+    let mut request = make_chunked_request(vec!["0123456789", "0123456789"]);
+    let error = aggregate(&mut request, 15).await.unwrap_err();
+
+    assert!(matches!(error, BodyError::TooLarge(15)));
+  }
+
+  #[tokio::test]
+  async fn rejects_content_length_over_the_limit() {
+    let mut request = Request::builder()
+      .header(CONTENT_LENGTH, "2048")
+      .body(Body::from("{}"))
+      .unwrap();
+    let error = aggregate(&mut request, 1024).await.unwrap_err();
+
+    assert!(matches!(error, BodyError::TooLarge(1024)));
+  }
+
+  #[tokio::test]
+  async fn rejects_invalid_utf8() {
+    let mut request = Request::builder()
+      .body(Body::from(vec![0xffu8, 0xfe, 0xfd]))
+      .unwrap();
+    let error = aggregate_string(&mut request, 1024).await.unwrap_err();
+
+    assert!(matches!(error, BodyError::Malformed(_)));
+  }
+}
@@ -0,0 +1,151 @@
+use http::response::Builder as HttpResponseBuilder;
+use http::StatusCode;
+use hyper::{body::Bytes, Body, Response};
+use serde_json::json;
+use std::sync::Arc;
+
+use super::body;
+use super::error::ApiError;
+use super::route_cache::CacheHealth;
+use super::route_format::{self, RouteFormat};
+use super::router::RouteRequest;
+use super::service::Service;
+
+/// Reports the server health. Routing works without the route cache, so
+/// an unreachable cache is reported as `degraded` with a `200 OK` rather
+/// than taking the server out of rotation
+pub async fn health(services: Arc<Service>, _: RouteRequest) -> Result<Response<Body>, ApiError> {
+  let (backend, cache_health) = services.cache_health().await;
+  let body = match cache_health {
+    CacheHealth::Up => json!({
+      "status": "ok",
+      "cache": { "backend": backend, "state": "up" },
+    }),
+    CacheHealth::Disabled => json!({
+      "status": "ok",
+      "cache": { "backend": backend, "state": "disabled" },
+    }),
+    CacheHealth::Down(reason) => json!({
+      "status": "degraded",
+      "cache": { "backend": backend, "state": "down", "reason": reason },
+    }),
+  };
+  Ok(
+    HttpResponseBuilder::new()
+      .header(http::header::CONTENT_TYPE, "application/json")
+      .status(StatusCode::OK)
+      .body(Body::from(body.to_string()))
+      .expect("Failed to build response"),
+  )
+}
+
+/// Reports counters of the server caches and route planner
+pub async fn metrics(services: Arc<Service>, _: RouteRequest) -> Result<Response<Body>, ApiError> {
+  let body = json!({
+    "poiCache": services.poi_cache_metrics().to_json(),
+    "planner": services.planner_metrics().to_json(),
+  });
+  Ok(
+    HttpResponseBuilder::new()
+      .header(http::header::CONTENT_TYPE, "application/json")
+      .status(StatusCode::OK)
+      .body(Body::from(body.to_string()))
+      .expect("Failed to build response"),
+  )
+}
+
+/// Responds the cached route for the `id` path parameter, cached routes
+/// are protobuf encoded `RouteResult` so the native format is not available
+pub async fn get_id(
+  services: Arc<Service>,
+  request: RouteRequest,
+) -> Result<Response<Body>, ApiError> {
+  let format = route_format(&request).await?;
+  if RouteFormat::Native == format {
+    return Err(ApiError::NotAcceptable(String::from(
+      "cached routes are not available in the native format",
+    )));
+  }
+  let locale = route_locale(&services, &request).await;
+  let id = String::from(request.param("id").unwrap_or_default());
+  match services.get_value(&id).await? {
+    Some(ret) => Ok(make_route_response(
+      format,
+      format.render(ret, services.guidance(), &locale)?,
+    )),
+    None => {
+      println!("get_id[{}]>ret=", request.trace_id);
+      Err(ApiError::RouteNotFound(id))
+    }
+  }
+}
+
+pub async fn get_path(
+  services: Arc<Service>,
+  request: RouteRequest,
+) -> Result<Response<Body>, ApiError> {
+  let format = route_format(&request).await?;
+  let locale = route_locale(&services, &request).await;
+  let body_data = read_bytes(&services, &request).await?;
+  let ret = services
+    .find_path(&body_data, format, &locale, &request.upstream_trace_ids)
+    .await?;
+  Ok(make_route_response(format, ret.1))
+}
+
+pub async fn get_path_from_json(
+  services: Arc<Service>,
+  request: RouteRequest,
+) -> Result<Response<Body>, ApiError> {
+  let format = route_format(&request).await?;
+  let locale = route_locale(&services, &request).await;
+  let body_data = read_body(&services, &request).await?;
+  let ret = services
+    .find_path_from_json(body_data, format, &locale, &request.upstream_trace_ids)
+    .await?;
+  Ok(make_route_response(format, ret.1))
+}
+
+fn make_route_response(format: RouteFormat, payload: Vec<u8>) -> Response<Body> {
+  HttpResponseBuilder::new()
+    .header(http::header::CONTENT_TYPE, format.content_type())
+    .status(StatusCode::OK)
+    .body(Body::from(payload))
+    .expect("Failed to build response")
+}
+
+async fn route_format(request: &RouteRequest) -> Result<RouteFormat, ApiError> {
+  let request_lock = request.request.lock().await;
+  RouteFormat::from_request(&request_lock)
+}
+
+/// Picks the instructions locale from the `lang` query parameter,
+/// otherwise from the `Accept-Language` header
+async fn route_locale(services: &Service, request: &RouteRequest) -> String {
+  let request_lock = request.request.lock().await;
+  let query = request_lock.uri().query().unwrap_or_default();
+  let lang = route_format::query_param(query, "lang");
+  let requested = match &lang {
+    Some(lang) => lang.as_str(),
+    None => request_lock
+      .headers()
+      .get(http::header::ACCEPT_LANGUAGE)
+      .and_then(|value| value.to_str().ok())
+      .unwrap_or_default(),
+  };
+  services.guidance().resolve_locale(requested)
+}
+
+async fn read_bytes(services: &Service, request: &RouteRequest) -> Result<Bytes, ApiError> {
+  let mut request_lock = request.request.lock().await;
+  let body_data = body::aggregate(&mut request_lock, services.max_body_size()).await?;
+  println!("read_bytes[{}]->{}", request.trace_id, body_data.len());
+  Ok(body_data)
+}
+
+async fn read_body(services: &Service, request: &RouteRequest) -> Result<String, ApiError> {
+  let mut request_lock = request.request.lock().await;
+  let body_data = body::aggregate_string(&mut request_lock, services.max_body_size()).await?;
+  println!("read_body[{}]->{}", request.trace_id, body_data.len());
+  Ok(body_data)
+}
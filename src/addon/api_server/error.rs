@@ -0,0 +1,288 @@
+use http::header::{HeaderValue, ALLOW, RETRY_AFTER};
+use http::{Method, StatusCode};
+use hyper::{Body, Response};
+use std::error::Error;
+use std::fmt::Display;
+
+use crate::utils::error::make_http_error_response_with_code;
+
+use super::body::BodyError;
+use super::route_planner::PlannerError;
+use super::routing_engine::RouteError;
+use super::search_poi::SearchError;
+use super::validation::ValidationErrors;
+
+/// Every failure an API endpoint responds with.
+///
+/// Each variant maps to a HTTP status and to a stable machine-readable
+/// code sent in the JSON error body
+#[derive(Debug)]
+pub enum ApiError {
+  /// The request is malformed
+  BadInput(String),
+  /// One or more fields of the request fail validation
+  Validation(ValidationErrors),
+  /// The request body exceeds the configured limit in bytes
+  PayloadTooLarge(usize),
+  /// No endpoint matches the request path
+  NotFound,
+  /// An endpoint matches the request path but not its method
+  MethodNotAllowed(Vec<Method>),
+  /// The requested representation is not available for the resource
+  NotAcceptable(String),
+  /// No route is cached for the requested route id
+  RouteNotFound(String),
+  /// The POI service has no POI for the requested id
+  PoiNotFound(String),
+  /// The POI service failed or answered with an unexpected payload
+  PoiUpstream(String),
+  /// The routing engine failed with the provided error code
+  RoutingEngine(i32),
+  /// No route connects the requested points
+  NoRoute,
+  /// The routing engine produced a route which can't be decoded
+  InvalidRoute(String),
+  /// Too many routes are being planned, holds the seconds to wait before
+  /// trying again
+  Overloaded(u64),
+  /// The route was not planned within the timeout, in milliseconds
+  RoutingTimeout(u64),
+  /// The route cache failed
+  Cache(String),
+}
+
+impl ApiError {
+  pub fn status(&self) -> StatusCode {
+    match self {
+      ApiError::BadInput(_) => StatusCode::BAD_REQUEST,
+      ApiError::Validation(_) => StatusCode::BAD_REQUEST,
+      ApiError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+      ApiError::NotFound => StatusCode::NOT_FOUND,
+      ApiError::MethodNotAllowed(_) => StatusCode::METHOD_NOT_ALLOWED,
+      ApiError::NotAcceptable(_) => StatusCode::NOT_ACCEPTABLE,
+      ApiError::RouteNotFound(_) => StatusCode::NOT_FOUND,
+      ApiError::PoiNotFound(_) => StatusCode::UNPROCESSABLE_ENTITY,
+      ApiError::PoiUpstream(_) => StatusCode::BAD_GATEWAY,
+      ApiError::RoutingEngine(_) => StatusCode::INTERNAL_SERVER_ERROR,
+      ApiError::NoRoute => StatusCode::UNPROCESSABLE_ENTITY,
+      ApiError::InvalidRoute(_) => StatusCode::INTERNAL_SERVER_ERROR,
+      ApiError::Overloaded(_) => StatusCode::SERVICE_UNAVAILABLE,
+      ApiError::RoutingTimeout(_) => StatusCode::GATEWAY_TIMEOUT,
+      ApiError::Cache(_) => StatusCode::SERVICE_UNAVAILABLE,
+    }
+  }
+
+  pub fn code(&self) -> &'static str {
+    match self {
+      ApiError::BadInput(_) => "BAD_INPUT",
+      ApiError::Validation(_) => "VALIDATION_FAILED",
+      ApiError::PayloadTooLarge(_) => "PAYLOAD_TOO_LARGE",
+      ApiError::NotFound => "NOT_FOUND",
+      ApiError::MethodNotAllowed(_) => "METHOD_NOT_ALLOWED",
+      ApiError::NotAcceptable(_) => "NOT_ACCEPTABLE",
+      ApiError::RouteNotFound(_) => "ROUTE_NOT_FOUND",
+      ApiError::PoiNotFound(_) => "POI_NOT_FOUND",
+      ApiError::PoiUpstream(_) => "POI_UPSTREAM_ERROR",
+      ApiError::RoutingEngine(_) => "ROUTING_ENGINE_ERROR",
+      ApiError::NoRoute => "NO_ROUTE",
+      ApiError::InvalidRoute(_) => "INVALID_ROUTE",
+      ApiError::Overloaded(_) => "OVERLOADED",
+      ApiError::RoutingTimeout(_) => "ROUTING_TIMEOUT",
+      ApiError::Cache(_) => "CACHE_ERROR",
+    }
+  }
+
+  /// Creates the JSON error response for this error
+  pub fn into_response(self, trace_id: &str) -> Response<Body> {
+    let errors = match &self {
+      ApiError::Validation(errors) => errors
+        .errors()
+        .iter()
+        .map(|error| error.to_string())
+        .collect(),
+      _ => Vec::new(),
+    };
+    let mut response = make_http_error_response_with_code(
+      self.status(),
+      self.code(),
+      self.to_string().as_str(),
+      trace_id,
+      errors,
+    );
+
+    if let ApiError::MethodNotAllowed(allowed) = &self {
+      let allowed = allowed
+        .iter()
+        .map(|method| method.as_str())
+        .collect::<Vec<&str>>()
+        .join(", ");
+      response.headers_mut().insert(
+        ALLOW,
+        HeaderValue::from_str(&allowed).expect("Failed to build Allow header"),
+      );
+    }
+
+    if let ApiError::Overloaded(retry_after) = &self {
+      response
+        .headers_mut()
+        .insert(RETRY_AFTER, HeaderValue::from(*retry_after));
+    }
+
+    response
+  }
+}
+
+impl Error for ApiError {}
+
+impl Display for ApiError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      ApiError::BadInput(reason) => write!(f, "bad input: {}", reason),
+      ApiError::Validation(errors) => write!(f, "invalid request: {}", errors),
+      ApiError::PayloadTooLarge(limit) => {
+        write!(f, "request body exceeds the limit of {} bytes", limit)
+      }
+      ApiError::NotFound => write!(f, "Not Found"),
+      ApiError::MethodNotAllowed(_) => write!(f, "Method Not Allowed"),
+      ApiError::NotAcceptable(reason) => write!(f, "not acceptable: {}", reason),
+      ApiError::RouteNotFound(id) => write!(f, "route not found: {}", id),
+      ApiError::PoiNotFound(id) => write!(f, "poi not found: {}", id),
+      ApiError::PoiUpstream(reason) => write!(f, "poi service error: {}", reason),
+      ApiError::RoutingEngine(code) => write!(f, "routing engine error: {}", code),
+      ApiError::NoRoute => write!(f, "no route between the requested points"),
+      ApiError::InvalidRoute(reason) => write!(f, "invalid route: {}", reason),
+      ApiError::Overloaded(_) => write!(f, "too many routes are being planned"),
+      ApiError::RoutingTimeout(timeout) => {
+        write!(f, "route not planned within {} ms", timeout)
+      }
+      ApiError::Cache(reason) => write!(f, "route cache error: {}", reason),
+    }
+  }
+}
+
+impl From<BodyError> for ApiError {
+  fn from(error: BodyError) -> Self {
+    match error {
+      BodyError::TooLarge(limit) => ApiError::PayloadTooLarge(limit),
+      BodyError::Malformed(reason) => ApiError::BadInput(reason),
+    }
+  }
+}
+
+impl From<ValidationErrors> for ApiError {
+  fn from(errors: ValidationErrors) -> Self {
+    ApiError::Validation(errors)
+  }
+}
+
+impl From<SearchError> for ApiError {
+  fn from(error: SearchError) -> Self {
+    match error {
+      SearchError::NotFound(id) => ApiError::PoiNotFound(id),
+      SearchError::Upstream(reason) => ApiError::PoiUpstream(reason),
+      SearchError::InvalidDetail(error) => ApiError::PoiUpstream(error.to_string()),
+    }
+  }
+}
+
+impl From<PlannerError> for ApiError {
+  fn from(error: PlannerError) -> Self {
+    match error {
+      PlannerError::Saturated(retry_after) => ApiError::Overloaded(retry_after),
+      PlannerError::TimedOut(timeout) => ApiError::RoutingTimeout(timeout),
+      PlannerError::Aborted => ApiError::InvalidRoute(error.to_string()),
+    }
+  }
+}
+
+impl From<RouteError> for ApiError {
+  fn from(error: RouteError) -> Self {
+    match error {
+      RouteError::Engine(code) => ApiError::RoutingEngine(code),
+      RouteError::EmptyOutput(_) => ApiError::InvalidRoute(error.to_string()),
+      RouteError::InvalidOutput(_) => ApiError::InvalidRoute(error.to_string()),
+      RouteError::InvalidParameter(reason) => ApiError::BadInput(reason),
+      RouteError::NoRoute => ApiError::NoRoute,
+      RouteError::Unsupported(_) => ApiError::NotAcceptable(error.to_string()),
+    }
+  }
+}
+
+#[cfg(test)]
+pub mod tests {
+  use super::*;
+
+  #[test]
+  fn maps_variants_to_status_and_code() {
+    let cases = vec![
+      (ApiError::BadInput(String::new()), 400, "BAD_INPUT"),
+      (ApiError::PoiNotFound(String::new()), 422, "POI_NOT_FOUND"),
+      (
+        ApiError::PoiUpstream(String::new()),
+        502,
+        "POI_UPSTREAM_ERROR",
+      ),
+      (ApiError::RoutingEngine(3), 500, "ROUTING_ENGINE_ERROR"),
+      (ApiError::NoRoute, 422, "NO_ROUTE"),
+      (ApiError::Cache(String::new()), 503, "CACHE_ERROR"),
+      (ApiError::Overloaded(1), 503, "OVERLOADED"),
+      (ApiError::RoutingTimeout(10000), 504, "ROUTING_TIMEOUT"),
+    ];
+
+    for (error, status, code) in cases {
+      assert_eq!(error.status().as_u16(), status);
+      assert_eq!(error.code(), code);
+    }
+  }
+
+  #[tokio::test]
+  async fn renders_json_envelope() {
+    let response = ApiError::RoutingEngine(3).into_response("trace");
+    let (parts, body) = response.into_parts();
+    let body = hyper::body::to_bytes(body).await.unwrap();
+    let body = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+
+    assert_eq!(parts.status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(parts.headers.get("x-trace-id").unwrap(), "trace");
+    assert_eq!(body["status_code"], 500);
+    assert_eq!(body["code"], "ROUTING_ENGINE_ERROR");
+    assert_eq!(body["trace_id"], "trace");
+    assert_eq!(body["message"], "routing engine error: 3");
+  }
+
+  #[tokio::test]
+  async fn lists_validation_errors() {
+    let mut errors = ValidationErrors::new();
+    errors.add("startPoint.latitude", "must be between -90 and 90");
+    errors.add("end", "either endPoint or endPoiID is required");
+
+    let response = ApiError::from(errors).into_response("trace");
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let body = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+
+    assert_eq!(body["status_code"], 400);
+    assert_eq!(body["code"], "VALIDATION_FAILED");
+    assert_eq!(
+      body["errors"],
+      serde_json::json!([
+        "startPoint.latitude: must be between -90 and 90",
+        "end: either endPoint or endPoiID is required"
+      ])
+    );
+  }
+
+  #[test]
+  fn advertises_allowed_methods() {
+    let response = ApiError::MethodNotAllowed(vec![Method::GET, Method::POST]).into_response("");
+
+    assert_eq!(response.headers().get(ALLOW).unwrap(), "GET, POST");
+  }
+
+  #[test]
+  fn asks_to_retry_when_overloaded() {
+    let response = ApiError::from(PlannerError::Saturated(2)).into_response("");
+
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.headers().get(RETRY_AFTER).unwrap(), "2");
+  }
+}
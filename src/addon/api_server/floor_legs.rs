@@ -0,0 +1,144 @@
+use serde_json::{json, Value};
+
+use crate::protos::route_result::Route;
+
+/// `RoadForm.form` of stairs, the `facility5` guidance phrase
+pub const FORM_STAIRS: u32 = 5;
+
+/// `RoadForm.form` of an escalator, the `facility7` guidance phrase
+pub const FORM_ESCALATOR: u32 = 7;
+
+/// `RoadForm.form` of an elevator, the `facility8` guidance phrase
+pub const FORM_ELEVATOR: u32 = 8;
+
+/// Facility type of a road form going from a floor to another
+pub fn facility_type(form: u32) -> Option<&'static str> {
+  match form {
+    FORM_STAIRS => Some("stairs"),
+    FORM_ESCALATOR => Some("escalator"),
+    FORM_ELEVATOR => Some("elevator"),
+    _ => None,
+  }
+}
+
+/// Change from a leg to the next one
+#[derive(Debug, Clone, PartialEq)]
+pub struct FloorTransition {
+  /// Form of the last road of the leg, the facility taken when it has a
+  /// `facility_type`
+  pub facility: u32,
+  pub to_model_id: u32,
+  pub to_floor: i32,
+}
+
+/// Part of a route walked on a single floor of a model, from the
+/// `roadPoints` at `from_index` to `to_index`
+#[derive(Debug, Clone, PartialEq)]
+pub struct FloorLeg {
+  pub model_id: u32,
+  pub floor: i32,
+  pub from_index: u32,
+  pub to_index: u32,
+  /// `None` on the last leg
+  pub transition: Option<FloorTransition>,
+}
+
+impl FloorLeg {
+  /// The leg in the casing of the protobuf canonical JSON mapping
+  pub fn to_json(&self) -> Value {
+    let transition = self.transition.as_ref().map(|transition| {
+      json!({
+        "facility": transition.facility,
+        "facilityType": facility_type(transition.facility),
+        "toModelID": transition.to_model_id,
+        "toFloor": transition.to_floor,
+      })
+    });
+    json!({
+      "modelID": self.model_id,
+      "floor": self.floor,
+      "fromIndex": self.from_index,
+      "toIndex": self.to_index,
+      "transition": transition,
+    })
+  }
+}
+
+/// Splits `route` where its `roadForms` change model or floor, so indoor
+/// map clients can switch the floor plan at each transition
+pub fn floor_legs(route: &Route) -> Vec<FloorLeg> {
+  let mut legs: Vec<FloorLeg> = Vec::new();
+  let mut last_form = 0;
+  for form in route.roadForms.iter() {
+    match legs.last_mut() {
+      Some(leg) if leg.model_id == form.modelID && leg.floor == form.floor => {
+        leg.to_index = form.toIndex;
+      }
+      previous => {
+        if let Some(leg) = previous {
+          leg.transition = Some(FloorTransition {
+            facility: last_form,
+            to_model_id: form.modelID,
+            to_floor: form.floor,
+          });
+        }
+        legs.push(FloorLeg {
+          model_id: form.modelID,
+          floor: form.floor,
+          from_index: form.fromIndex,
+          to_index: form.toIndex,
+          transition: None,
+        });
+      }
+    }
+    last_form = form.form;
+  }
+  legs
+}
+
+#[cfg(test)]
+pub mod tests {
+  use super::*;
+  use crate::protos::route_result::RoadForm;
+
+  fn make_form(from_index: u32, to_index: u32, form: u32, model_id: u32, floor: i32) -> RoadForm {
+    let mut road_form = RoadForm::new();
+    road_form.fromIndex = from_index;
+    road_form.toIndex = to_index;
+    road_form.form = form;
+    road_form.modelID = model_id;
+    road_form.floor = floor;
+    road_form
+  }
+
+  #[test]
+  fn splits_routes_by_floor() {
+    let mut route = Route::new();
+    route.roadForms.push(make_form(0, 2, 0, 0, 0));
+    route.roadForms.push(make_form(2, 4, 0, 12, 1));
+    route.roadForms.push(make_form(4, 5, FORM_ESCALATOR, 12, 1));
+    route.roadForms.push(make_form(5, 6, 0, 12, 2));
+    route.roadForms.push(make_form(6, 8, 3, 12, 2));
+
+    let legs = floor_legs(&route);
+    assert_eq!(
+      legs
+        .iter()
+        .map(|leg| (leg.model_id, leg.floor, leg.from_index, leg.to_index))
+        .collect::<Vec<_>>(),
+      vec![(0, 0, 0, 2), (12, 1, 2, 5), (12, 2, 5, 8)]
+    );
+    assert_eq!(legs[2].transition, None);
+    assert_eq!(
+      legs[1].to_json()["transition"],
+      json!({
+        "facility": 7,
+        "facilityType": "escalator",
+        "toModelID": 12,
+        "toFloor": 2,
+      })
+    );
+    assert_eq!(legs[0].to_json()["transition"]["facilityType"], Value::Null);
+    assert_eq!(floor_legs(&Route::new()), vec![]);
+  }
+}
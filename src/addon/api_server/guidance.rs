@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use crate::protos::route_result::{Route, WalkGuidePoint};
+
+/// Locale used when the client asks for none or for an unknown one
+pub const DEFAULT_LOCALE: &str = "zh_cn";
+
+type Phrases = HashMap<String, String>;
+
+/// Turn-by-turn instructions built from the `guiding` phrases of the
+/// routing library `config.json`, one phrase table per locale
+#[derive(Debug, Default, Clone)]
+pub struct Guidance {
+  locales: HashMap<String, Phrases>,
+}
+
+impl Guidance {
+  /// Loads the phrases from a routing library `config.json`, a missing or
+  /// broken file leaves the guidance empty so routes are served without
+  /// instructions
+  pub fn load(path: &str) -> Guidance {
+    let config = std::fs::read_to_string(path)
+      .map_err(|error| error.to_string())
+      .and_then(|config| Guidance::from_json(&config).map_err(|error| error.to_string()));
+    match config {
+      Ok(guidance) => guidance,
+      Err(error) => {
+        println!("Guidance::load[{}]->{}", path, error);
+        Guidance::default()
+      }
+    }
+  }
+
+  pub fn from_json(config: &str) -> serde_json::Result<Guidance> {
+    let config = serde_json::from_str::<Value>(config)?;
+    let mut locales = HashMap::new();
+    if let Some(guiding) = config["guiding"].as_object() {
+      for (locale, phrases) in guiding {
+        let phrases = phrases
+          .as_object()
+          .map(|phrases| {
+            phrases
+              .iter()
+              .filter_map(|(key, value)| Some((key.clone(), String::from(value.as_str()?))))
+              .collect::<Phrases>()
+          })
+          .unwrap_or_default();
+        locales.insert(locale.to_lowercase(), phrases);
+      }
+    }
+    Ok(Guidance { locales })
+  }
+
+  /// Picks the loaded locale best matching the client preferences, given
+  /// either as a single tag (`en`, `zh-CN`) or as an `Accept-Language`
+  /// list. Falls back to `DEFAULT_LOCALE`
+  pub fn resolve_locale(&self, requested: &str) -> String {
+    let candidates = requested
+      .split(',')
+      .filter_map(|tag| tag.split(';').next())
+      .map(|tag| tag.trim().to_lowercase().replace('-', "_"))
+      .filter(|tag| !tag.is_empty());
+
+    for tag in candidates {
+      if self.locales.contains_key(&tag) {
+        return tag;
+      }
+      let language = tag.split('_').next().unwrap_or_default();
+      let mut matches = self
+        .locales
+        .keys()
+        .filter(|locale| locale.split('_').next() == Some(language))
+        .collect::<Vec<&String>>();
+      matches.sort();
+      if let Some(locale) = matches.first() {
+        return String::from(locale.as_str());
+      }
+    }
+    String::from(DEFAULT_LOCALE)
+  }
+
+  pub fn has_locale(&self, locale: &str) -> bool {
+    self.locales.contains_key(locale)
+  }
+
+  /// One instruction per guide point of the route
+  pub fn route_instructions(&self, locale: &str, route: &Route) -> Vec<String> {
+    route
+      .guidePoints
+      .iter()
+      .map(|guide_point| self.instruction(locale, guide_point))
+      .collect()
+  }
+
+  /// Describes a guide point as the walk along its in link, the turn and
+  /// the facility or link it leads to
+  pub fn instruction(&self, locale: &str, guide_point: &WalkGuidePoint) -> String {
+    let phrases = match self.locales.get(locale) {
+      Some(phrases) => phrases,
+      None => return String::new(),
+    };
+    let phrase = |key: String| phrases.get(&key).map(String::as_str).unwrap_or_default();
+
+    let direction = match direction_key(guide_point) {
+      Some(key) => phrase(key),
+      None => phrase(String::from("gostraight")),
+    };
+    let facility = phrase(format!("facility{}", guide_point.outLinkForm));
+
+    if locale.starts_with("zh") {
+      chinese_instruction(guide_point, direction, facility)
+    } else {
+      english_instruction(guide_point, direction, facility)
+    }
+  }
+}
+
+/// The `directionN` phrase for the guide point, taken from `guideType`
+/// when it is one of the known directions, otherwise from the clockwise
+/// turn `angle` in degrees
+fn direction_key(guide_point: &WalkGuidePoint) -> Option<String> {
+  let direction = match guide_point.guideType {
+    1..=10 => guide_point.guideType,
+    _ => match guide_point.angle % 360 {
+      0 if 0 == guide_point.angle => return None,
+      0..=20 | 340..=359 => 1,
+      21..=70 => 8,
+      71..=135 => 7,
+      136..=179 => 6,
+      180 => 5,
+      181..=224 => 4,
+      225..=289 => 3,
+      _ => 2,
+    },
+  };
+  Some(format!("direction{}", direction))
+}
+
+fn chinese_instruction(guide_point: &WalkGuidePoint, direction: &str, facility: &str) -> String {
+  let mut instruction = String::new();
+  if 0 < guide_point.inLinkLen {
+    if !guide_point.inLinkName.is_empty() {
+      instruction += &format!("沿{}", guide_point.inLinkName);
+    }
+    instruction += &format!("走{}米后", guide_point.inLinkLen);
+  }
+  instruction += direction;
+  if !facility.is_empty() {
+    instruction += &format!("，经过{}", facility);
+  }
+  if !guide_point.outLinkName.is_empty() {
+    instruction += &format!("，进入{}", guide_point.outLinkName);
+  }
+  instruction
+}
+
+fn english_instruction(guide_point: &WalkGuidePoint, direction: &str, facility: &str) -> String {
+  let mut instruction = String::new();
+  if 0 < guide_point.inLinkLen {
+    instruction += &format!("Walk {} m", guide_point.inLinkLen);
+    if !guide_point.inLinkName.is_empty() {
+      instruction += &format!(" along {}", guide_point.inLinkName);
+    }
+    instruction += &format!(", then {}", direction);
+  } else {
+    let mut chars = direction.chars();
+    if let Some(first) = chars.next() {
+      instruction += &first.to_uppercase().collect::<String>();
+      instruction += chars.as_str();
+    }
+  }
+  if !facility.is_empty() {
+    instruction += &format!(", take the {}", facility);
+  }
+  if !guide_point.outLinkName.is_empty() {
+    instruction += &format!(" onto {}", guide_point.outLinkName);
+  }
+  instruction
+}
+
+#[cfg(test)]
+pub mod tests {
+  use super::*;
+
+  fn make_guidance() -> Guidance {
+    Guidance::load("routinglib/config/config.json")
+  }
+
+  fn make_guide_point() -> WalkGuidePoint {
+    let mut guide_point = WalkGuidePoint::new();
+    guide_point.guideType = 7;
+    guide_point.inLinkLen = 120;
+    guide_point.inLinkName = String::from("长安街");
+    guide_point.outLinkName = String::from("王府井大街");
+    guide_point.outLinkForm = 1;
+    guide_point
+  }
+
+  #[test]
+  fn loads_phrases_from_config() {
+    let guidance = make_guidance();
+
+    assert!(guidance.has_locale("zh_cn"));
+    assert!(guidance.has_locale("en"));
+  }
+
+  #[test]
+  fn resolves_locales() {
+    let guidance = make_guidance();
+
+    assert_eq!(guidance.resolve_locale("en"), "en");
+    assert_eq!(guidance.resolve_locale("en-US,en;q=0.9"), "en");
+    assert_eq!(guidance.resolve_locale("zh-CN"), "zh_cn");
+    assert_eq!(guidance.resolve_locale("fr, zh;q=0.8"), "zh_cn");
+    assert_eq!(guidance.resolve_locale("fr"), DEFAULT_LOCALE);
+    assert_eq!(guidance.resolve_locale(""), DEFAULT_LOCALE);
+  }
+
+  #[test]
+  fn builds_instructions() {
+    let guidance = make_guidance();
+    let guide_point = make_guide_point();
+
+    assert_eq!(
+      guidance.instruction("zh_cn", &guide_point),
+      "沿长安街走120米后右转，经过天桥，进入王府井大街"
+    );
+    assert_eq!(
+      guidance.instruction("en", &guide_point),
+      "Walk 120 m along 长安街, then turn right, take the footbridge onto 王府井大街"
+    );
+  }
+
+  #[test]
+  fn falls_back_to_the_angle() {
+    let guidance = make_guidance();
+    let mut guide_point = WalkGuidePoint::new();
+    guide_point.angle = 270;
+
+    assert_eq!(guidance.instruction("en", &guide_point), "Turn left");
+    guide_point.angle = 0;
+    assert_eq!(
+      guidance.instruction("en", &guide_point),
+      "Continue straight"
+    );
+  }
+}
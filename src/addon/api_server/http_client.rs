@@ -0,0 +1,323 @@
+use hyper::body::Bytes;
+use hyper::client::HttpConnector;
+use hyper::{Body, Client, Method, Request, StatusCode, Uri};
+use hyper_rustls::HttpsConnector;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Display;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::config::PoiClientConfig;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HttpClientError {
+  /// The host failed too often, requests fail fast until it cools down
+  CircuitOpen(String),
+  /// The attempt did not complete within the request timeout
+  Timeout,
+  /// The request could not be sent or its response could not be read
+  Transport(String),
+}
+
+impl Error for HttpClientError {}
+
+impl Display for HttpClientError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      HttpClientError::CircuitOpen(host) => write!(f, "circuit open for {}", host),
+      HttpClientError::Timeout => write!(f, "request timed out"),
+      HttpClientError::Transport(reason) => write!(f, "{}", reason),
+    }
+  }
+}
+
+/// Consecutive failures of a host. Once `threshold` is reached the circuit
+/// opens and requests are rejected until `cooldown` elapses, then one
+/// request is let through per `cooldown` until one succeeds
+#[derive(Debug, Default)]
+struct CircuitBreaker {
+  failures: u32,
+  open_until: Option<Instant>,
+  /// A request probes the host after the circuit opened
+  half_open: bool,
+}
+
+impl CircuitBreaker {
+  fn allow(&mut self, now: Instant, cooldown: Duration) -> bool {
+    match self.open_until {
+      None => true,
+      Some(open_until) if now < open_until => false,
+      Some(_) => {
+        // a probe which never reports back lets another one through after
+        // the next cooldown
+        self.half_open = true;
+        self.open_until = Some(now + cooldown);
+        true
+      }
+    }
+  }
+
+  fn succeed(&mut self) {
+    *self = CircuitBreaker::default();
+  }
+
+  fn fail(&mut self, now: Instant, threshold: u32, cooldown: Duration) {
+    self.failures = self.failures.saturating_add(1);
+    if self.half_open || self.failures >= threshold {
+      self.half_open = false;
+      self.open_until = Some(now + cooldown);
+    }
+  }
+}
+
+/// A random duration between half and all of `delay`, so clients failing
+/// together do not retry together
+fn jitter(delay: Duration) -> Duration {
+  // every `RandomState` is seeded differently
+  let random = RandomState::new().build_hasher().finish();
+  let half = delay / 2;
+  let spread = half.as_millis() as u64 + 1;
+  half + Duration::from_millis(random % spread)
+}
+
+/// Delay before the `retry`-th retry, doubling from `retry_backoff` up to
+/// `max_retry_backoff` before the jitter
+fn retry_delay(config: &PoiClientConfig, retry: u32) -> Duration {
+  let exponent = std::cmp::min(retry.saturating_sub(1), 16);
+  let delay = config.retry_backoff.saturating_mul(1 << exponent);
+  jitter(Duration::from_millis(std::cmp::min(
+    delay,
+    std::cmp::max(config.max_retry_backoff, config.retry_backoff),
+  )))
+}
+
+/// HTTP and HTTPS client shared by the requests of a `Service`, keeping
+/// connections alive between requests. Idempotent requests failing or
+/// answered with a server error are retried, and hosts failing repeatedly
+/// are given a rest by a per host circuit breaker
+#[derive(Clone)]
+pub struct HttpClient {
+  client: Client<HttpsConnector<HttpConnector>>,
+  config: PoiClientConfig,
+  breakers: Arc<Mutex<HashMap<String, CircuitBreaker>>>,
+}
+
+impl std::fmt::Debug for HttpClient {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("HttpClient")
+      .field("config", &self.config)
+      .finish()
+  }
+}
+
+impl Default for HttpClient {
+  fn default() -> Self {
+    HttpClient::new(PoiClientConfig::default())
+  }
+}
+
+impl HttpClient {
+  /// Creates the client without connecting, server certificates are
+  /// verified against the Mozilla root certificates
+  pub fn new(config: PoiClientConfig) -> HttpClient {
+    let mut http = HttpConnector::new();
+    http.enforce_http(false);
+    if 0 < config.connect_timeout {
+      http.set_connect_timeout(Some(Duration::from_millis(config.connect_timeout)));
+    }
+    http.set_nodelay(true);
+
+    let mut tls = rustls::ClientConfig::new();
+    tls
+      .root_store
+      .add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS);
+    tls.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+    let client = Client::builder()
+      .pool_idle_timeout(Duration::from_secs(config.pool_idle_timeout))
+      .pool_max_idle_per_host(config.pool_max_idle_per_host)
+      .build(HttpsConnector::from((http, tls)));
+    HttpClient {
+      client,
+      config,
+      breakers: Arc::new(Mutex::new(HashMap::new())),
+    }
+  }
+
+  /// Sends `body` to `uri` and reads the whole response. A request is only
+  /// retried when `idempotent`, a server error is answered as is once the
+  /// retries are exhausted
+  pub async fn request(
+    &self,
+    method: Method,
+    uri: &str,
+    content_type: &str,
+    body: Bytes,
+    idempotent: bool,
+  ) -> Result<(StatusCode, Bytes), HttpClientError> {
+    let uri = uri
+      .parse::<Uri>()
+      .map_err(|error| HttpClientError::Transport(error.to_string()))?;
+    let host = uri
+      .authority()
+      .map(|authority| authority.to_string())
+      .unwrap_or_default();
+    let retries = if idempotent { self.config.retries } else { 0 };
+    let mut retry = 0;
+    loop {
+      self.admit(&host)?;
+      let ret = self
+        .send(method.clone(), &uri, content_type, body.clone())
+        .await;
+      let failed = match &ret {
+        Ok((status, _)) => status.is_server_error(),
+        Err(_) => true,
+      };
+      self.report(&host, failed);
+      if !failed || retry >= retries {
+        return ret;
+      }
+      retry += 1;
+      let delay = retry_delay(&self.config, retry);
+      println!(
+        "HttpClient::request[{}]->retry {} in {:?}",
+        host, retry, delay
+      );
+      tokio::time::sleep(delay).await;
+    }
+  }
+
+  /// One attempt, bounded by the request timeout
+  async fn send(
+    &self,
+    method: Method,
+    uri: &Uri,
+    content_type: &str,
+    body: Bytes,
+  ) -> Result<(StatusCode, Bytes), HttpClientError> {
+    let req = Request::builder()
+      .method(method)
+      .uri(uri.clone())
+      .header(hyper::header::CONTENT_TYPE, content_type)
+      .body(Body::from(body))
+      .map_err(|error| HttpClientError::Transport(error.to_string()))?;
+    let exchange = async {
+      let mut response = self
+        .client
+        .request(req)
+        .await
+        .map_err(|error| HttpClientError::Transport(error.to_string()))?;
+      let bytes = hyper::body::to_bytes(response.body_mut())
+        .await
+        .map_err(|error| HttpClientError::Transport(error.to_string()))?;
+      Ok((response.status(), bytes))
+    };
+    if 0 == self.config.request_timeout {
+      return exchange.await;
+    }
+    tokio::time::timeout(Duration::from_millis(self.config.request_timeout), exchange)
+      .await
+      .map_err(|_| HttpClientError::Timeout)?
+  }
+
+  fn admit(&self, host: &str) -> Result<(), HttpClientError> {
+    if 0 == self.config.breaker_threshold {
+      return Ok(());
+    }
+    let mut breakers = self.breakers.lock().expect("circuit breakers");
+    let breaker = breakers.entry(String::from(host)).or_default();
+    if breaker.allow(Instant::now(), self.breaker_cooldown()) {
+      Ok(())
+    } else {
+      Err(HttpClientError::CircuitOpen(String::from(host)))
+    }
+  }
+
+  fn report(&self, host: &str, failed: bool) {
+    if 0 == self.config.breaker_threshold {
+      return;
+    }
+    let mut breakers = self.breakers.lock().expect("circuit breakers");
+    let breaker = breakers.entry(String::from(host)).or_default();
+    if failed {
+      breaker.fail(
+        Instant::now(),
+        self.config.breaker_threshold,
+        self.breaker_cooldown(),
+      );
+    } else {
+      breaker.succeed();
+    }
+  }
+
+  fn breaker_cooldown(&self) -> Duration {
+    Duration::from_millis(self.config.breaker_cooldown)
+  }
+}
+
+#[cfg(test)]
+pub mod tests {
+  use super::*;
+
+  #[test]
+  fn opens_circuit_after_threshold() {
+    let cooldown = Duration::from_secs(30);
+    let now = Instant::now();
+    let mut breaker = CircuitBreaker::default();
+    breaker.fail(now, 2, cooldown);
+    assert!(breaker.allow(now, cooldown));
+
+    breaker.fail(now, 2, cooldown);
+    assert!(!breaker.allow(now + Duration::from_secs(29), cooldown));
+
+    // one probe once cooled down, its failure opens the circuit again
+    let later = now + cooldown;
+    assert!(breaker.allow(later, cooldown));
+    assert!(!breaker.allow(later, cooldown));
+    breaker.fail(later, 2, cooldown);
+    assert!(!breaker.allow(later + Duration::from_secs(29), cooldown));
+
+    let probe = later + cooldown;
+    assert!(breaker.allow(probe, cooldown));
+    breaker.succeed();
+    assert!(breaker.allow(probe, cooldown));
+    assert_eq!(breaker.failures, 0);
+  }
+
+  #[test]
+  fn backs_off_with_jitter() {
+    let config = PoiClientConfig::default();
+    for _ in 0..100 {
+      let first = retry_delay(&config, 1);
+      assert!(first >= Duration::from_millis(50) && first <= Duration::from_millis(100));
+      let last = retry_delay(&config, 30);
+      assert!(last >= Duration::from_millis(1000) && last <= Duration::from_millis(2000));
+    }
+  }
+
+  #[tokio::test]
+  async fn fails_fast_once_circuit_is_open() {
+    let mut config = PoiClientConfig::default();
+    config.retries = 1;
+    config.retry_backoff = 1;
+    config.breaker_threshold = 2;
+    let client = HttpClient::new(config);
+    let uri = "http://127.0.0.1:9/getPoiDetailByPoiId";
+
+    let ret = client
+      .request(Method::POST, uri, "application/json", Bytes::new(), true)
+      .await;
+    assert!(matches!(ret, Err(HttpClientError::Transport(_))));
+
+    let ret = client
+      .request(Method::POST, uri, "application/json", Bytes::new(), true)
+      .await;
+    assert_eq!(
+      ret,
+      Err(HttpClientError::CircuitOpen(String::from("127.0.0.1:9")))
+    );
+  }
+}
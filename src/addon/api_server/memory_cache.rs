@@ -0,0 +1,193 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::route_cache::{CacheFuture, CacheHealth, HealthFuture, KvCache};
+
+struct MemoryEntry {
+  value: Vec<u8>,
+  expires_at: Option<Instant>,
+  /// Position in the recency order, the lowest is the least recently used
+  used: u64,
+}
+
+#[derive(Default)]
+struct MemoryState {
+  entries: HashMap<String, MemoryEntry>,
+  recency: BTreeMap<u64, String>,
+  bytes: usize,
+  clock: u64,
+}
+
+impl MemoryState {
+  fn remove(&mut self, key: &str) {
+    if let Some(entry) = self.entries.remove(key) {
+      self.recency.remove(&entry.used);
+      self.bytes -= key.len() + entry.value.len();
+    }
+  }
+
+  fn touch(&mut self, key: &str) {
+    self.clock += 1;
+    let clock = self.clock;
+    if let Some(entry) = self.entries.get_mut(key) {
+      self.recency.remove(&entry.used);
+      entry.used = clock;
+      self.recency.insert(clock, String::from(key));
+    }
+  }
+}
+
+/// In-process `KvCache`, least recently used entries are evicted once
+/// `max_entries` or `max_bytes` is reached and expired entries are never
+/// returned. Entries are lost on restart and not shared between servers
+pub struct MemoryKvCache {
+  state: Mutex<MemoryState>,
+  max_entries: usize,
+  max_bytes: usize,
+}
+
+impl std::fmt::Debug for MemoryKvCache {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("MemoryKvCache")
+      .field("max_entries", &self.max_entries)
+      .field("max_bytes", &self.max_bytes)
+      .finish()
+  }
+}
+
+impl MemoryKvCache {
+  /// `0` leaves the corresponding limit out
+  pub fn new(max_entries: usize, max_bytes: usize) -> MemoryKvCache {
+    MemoryKvCache {
+      state: Mutex::new(MemoryState::default()),
+      max_entries,
+      max_bytes,
+    }
+  }
+
+  fn get_at(&self, key: &str, now: Instant) -> Option<Vec<u8>> {
+    let mut state = self.state.lock().expect("memory cache");
+    let expired = match state.entries.get(key) {
+      Some(entry) => entry
+        .expires_at
+        .map_or(false, |expires_at| expires_at <= now),
+      None => return None,
+    };
+    if expired {
+      state.remove(key);
+      return None;
+    }
+    state.touch(key);
+    state.entries.get(key).map(|entry| entry.value.clone())
+  }
+
+  fn set_at(&self, key: &str, value: &[u8], ttl: u64, now: Instant) {
+    let size = key.len() + value.len();
+    if 0 < self.max_bytes && size > self.max_bytes {
+      return;
+    }
+    let mut state = self.state.lock().expect("memory cache");
+    state.remove(key);
+    state.entries.insert(
+      String::from(key),
+      MemoryEntry {
+        value: value.to_vec(),
+        expires_at: if 0 < ttl {
+          Some(now + Duration::from_secs(ttl))
+        } else {
+          None
+        },
+        used: 0,
+      },
+    );
+    state.bytes += size;
+    state.touch(key);
+
+    while (0 < self.max_entries && state.entries.len() > self.max_entries)
+      || (0 < self.max_bytes && state.bytes > self.max_bytes)
+    {
+      let oldest = match state.recency.values().next() {
+        Some(oldest) => oldest.clone(),
+        None => break,
+      };
+      state.remove(&oldest);
+    }
+  }
+}
+
+impl KvCache for MemoryKvCache {
+  fn backend(&self) -> &'static str {
+    "memory"
+  }
+
+  fn get<'a>(&'a self, key: &'a str) -> CacheFuture<'a, Option<Vec<u8>>> {
+    let value = self.get_at(key, Instant::now());
+    Box::pin(async move { Ok(value) })
+  }
+
+  fn set<'a>(&'a self, key: &'a str, value: &'a [u8], ttl: u64) -> CacheFuture<'a, ()> {
+    self.set_at(key, value, ttl, Instant::now());
+    Box::pin(async { Ok(()) })
+  }
+
+  fn health(&self) -> HealthFuture<'_> {
+    Box::pin(async { CacheHealth::Up })
+  }
+}
+
+#[cfg(test)]
+pub mod tests {
+  use super::*;
+
+  #[test]
+  fn expires_entries() {
+    let cache = MemoryKvCache::new(0, 0);
+    let now = Instant::now();
+    cache.set_at("route", b"payload", 60, now);
+    cache.set_at("forever", b"payload", 0, now);
+
+    assert_eq!(
+      cache.get_at("route", now + Duration::from_secs(59)),
+      Some(b"payload".to_vec())
+    );
+    assert_eq!(cache.get_at("route", now + Duration::from_secs(60)), None);
+    assert_eq!(
+      cache.get_at("forever", now + Duration::from_secs(3600)),
+      Some(b"payload".to_vec())
+    );
+    assert_eq!(cache.state.lock().unwrap().entries.len(), 1);
+  }
+
+  #[test]
+  fn evicts_least_recently_used() {
+    let cache = MemoryKvCache::new(2, 0);
+    let now = Instant::now();
+    cache.set_at("a", b"1", 0, now);
+    cache.set_at("b", b"2", 0, now);
+    cache.get_at("a", now);
+    cache.set_at("c", b"3", 0, now);
+
+    assert_eq!(cache.get_at("a", now), Some(b"1".to_vec()));
+    assert_eq!(cache.get_at("b", now), None);
+    assert_eq!(cache.get_at("c", now), Some(b"3".to_vec()));
+  }
+
+  #[test]
+  fn limits_bytes() {
+    let cache = MemoryKvCache::new(0, 8);
+    let now = Instant::now();
+    cache.set_at("a", b"123", 0, now);
+    cache.set_at("b", b"456", 0, now);
+    cache.set_at("c", b"78", 0, now);
+    cache.set_at("big", b"0123456789", 0, now);
+
+    assert_eq!(cache.get_at("a", now), None);
+    assert_eq!(cache.get_at("b", now), Some(b"456".to_vec()));
+    assert_eq!(cache.get_at("c", now), Some(b"78".to_vec()));
+    assert_eq!(cache.get_at("big", now), None);
+
+    cache.set_at("b", b"4", 0, now);
+    assert_eq!(cache.state.lock().unwrap().bytes, 5);
+  }
+}
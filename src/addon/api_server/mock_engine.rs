@@ -0,0 +1,390 @@
+use protobuf::{RepeatedField, SingularPtrField};
+use serde::Deserialize;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::config::{EngineConfig, DEFAULT_WALK_MODE};
+use crate::protos::route_result::{RoadDescribe, RoadForm, Route, RouteResult};
+use crate::protos::route_server_param::RoutePlanServerParameter;
+
+use super::road_graph::distance;
+use super::routing_engine::{self, RouteError, RoutingEngine, WALK_SPEED};
+
+/// Speed of every other route in meters per second
+const DRIVE_SPEED: f64 = 10.0;
+
+#[derive(Debug, Clone, Deserialize)]
+struct MockNode {
+  id: u64,
+  longitude: f64,
+  latitude: f64,
+}
+
+impl MockNode {
+  fn location(&self) -> (f64, f64) {
+    (self.longitude, self.latitude)
+  }
+}
+
+/// Fixture graph file, edges are undirected pairs of node ids
+#[derive(Debug, Deserialize)]
+struct MockGraphFile {
+  nodes: Vec<MockNode>,
+  edges: Vec<(u64, u64)>,
+}
+
+/// Candidate of the shortest path search, ordered by shortest distance
+/// first
+#[derive(Debug, PartialEq)]
+struct Candidate {
+  distance: f64,
+  node: usize,
+}
+
+impl Eq for Candidate {}
+
+impl Ord for Candidate {
+  fn cmp(&self, other: &Self) -> Ordering {
+    other
+      .distance
+      .partial_cmp(&self.distance)
+      .unwrap_or(Ordering::Equal)
+      .then_with(|| other.node.cmp(&self.node))
+  }
+}
+
+impl PartialOrd for Candidate {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+/// Small graph the mock engine routes on, edges weigh their length
+#[derive(Debug, Default)]
+pub struct MockGraph {
+  nodes: Vec<MockNode>,
+  adjacency: Vec<Vec<(usize, f64)>>,
+}
+
+impl MockGraph {
+  pub fn from_json(json: &str) -> Result<MockGraph, String> {
+    let file = serde_json::from_str::<MockGraphFile>(json).map_err(|error| error.to_string())?;
+    let index = file
+      .nodes
+      .iter()
+      .enumerate()
+      .map(|(index, node)| (node.id, index))
+      .collect::<HashMap<u64, usize>>();
+    let mut adjacency = vec![Vec::new(); file.nodes.len()];
+    for (from, to) in file.edges {
+      let (from, to) = match (index.get(&from), index.get(&to)) {
+        (Some(from), Some(to)) => (*from, *to),
+        _ => return Err(format!("edge ({}, {}) has an unknown node", from, to)),
+      };
+      let length = distance(file.nodes[from].location(), file.nodes[to].location());
+      adjacency[from].push((to, length));
+      adjacency[to].push((from, length));
+    }
+    Ok(MockGraph {
+      nodes: file.nodes,
+      adjacency,
+    })
+  }
+
+  pub fn load(path: &str) -> Result<MockGraph, String> {
+    let json = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+    MockGraph::from_json(&json)
+  }
+
+  /// The node closest to `location`, `None` when the graph is empty
+  fn nearest(&self, location: (f64, f64)) -> Option<usize> {
+    (0..self.nodes.len()).min_by(|a, b| {
+      let a = distance(self.nodes[*a].location(), location);
+      let b = distance(self.nodes[*b].location(), location);
+      a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+    })
+  }
+
+  /// Nodes of the shortest path from `from` to `to`, both included
+  fn shortest_path(&self, from: usize, to: usize) -> Option<Vec<usize>> {
+    let mut distances = vec![f64::INFINITY; self.nodes.len()];
+    let mut previous = vec![None; self.nodes.len()];
+    let mut candidates = BinaryHeap::new();
+    distances[from] = 0.0;
+    candidates.push(Candidate {
+      distance: 0.0,
+      node: from,
+    });
+    while let Some(Candidate { distance, node }) = candidates.pop() {
+      if node == to {
+        break;
+      }
+      if distance > distances[node] {
+        continue;
+      }
+      for (next, length) in &self.adjacency[node] {
+        let next_distance = distance + length;
+        if next_distance < distances[*next] {
+          distances[*next] = next_distance;
+          previous[*next] = Some(node);
+          candidates.push(Candidate {
+            distance: next_distance,
+            node: *next,
+          });
+        }
+      }
+    }
+    if distances[to].is_infinite() {
+      return None;
+    }
+    let mut path = vec![to];
+    while let Some(node) = previous[*path.last().expect("path")] {
+      path.push(node);
+    }
+    path.reverse();
+    Some(path)
+  }
+}
+
+/// `RoutingEngine` planning without any routing data, for development and
+/// tests.
+///
+/// Routes go in a straight line through the requested points, or along the
+/// shortest path of a fixture graph between the nodes closest to them.
+/// Identical requests get identical routes and route ids
+#[derive(Debug)]
+pub struct MockEngine {
+  graph: Option<MockGraph>,
+  /// Routes in this mode walk, the others drive
+  walk_mode: u32,
+}
+
+impl Default for MockEngine {
+  fn default() -> Self {
+    MockEngine::new(None)
+  }
+}
+
+impl MockEngine {
+  pub fn new(graph: Option<MockGraph>) -> MockEngine {
+    MockEngine {
+      graph,
+      walk_mode: DEFAULT_WALK_MODE,
+    }
+  }
+
+  /// Routes on the fixture graph at `config.graph`, in straight lines when
+  /// it is empty. Fails when the graph can't be loaded
+  pub fn from_config(config: &EngineConfig) -> Result<MockEngine, String> {
+    let graph = if config.graph.is_empty() {
+      None
+    } else {
+      match MockGraph::load(&config.graph) {
+        Ok(graph) => Some(graph),
+        Err(error) => return Err(format!("mock engine graph {}: {}", config.graph, error)),
+      }
+    };
+    Ok(MockEngine {
+      graph,
+      walk_mode: config.walk_mode,
+    })
+  }
+
+  /// Locations from `from` to `to`, both included
+  fn plan_leg(&self, from: (f64, f64), to: (f64, f64)) -> Result<Vec<(f64, f64)>, RouteError> {
+    let graph = match &self.graph {
+      Some(graph) => graph,
+      None => return Ok(vec![from, to]),
+    };
+    let path = match (graph.nearest(from), graph.nearest(to)) {
+      (Some(start), Some(end)) => graph.shortest_path(start, end),
+      _ => None,
+    }
+    .ok_or(RouteError::NoRoute)?;
+
+    let mut leg = vec![from];
+    leg.extend(path.iter().map(|node| graph.nodes[*node].location()));
+    leg.push(to);
+    Ok(leg)
+  }
+}
+
+impl RoutingEngine for MockEngine {
+  fn name(&self) -> &'static str {
+    "mock"
+  }
+
+  fn find_path(
+    &self,
+    params: &RoutePlanServerParameter,
+  ) -> Result<(String, RouteResult), RouteError> {
+    let waypoints = routing_engine::waypoints(params)?;
+    let start = waypoints[0].clone();
+    let end = waypoints[waypoints.len() - 1].clone();
+
+    let mut locations: Vec<(f64, f64)> = Vec::new();
+    for leg in waypoints.windows(2) {
+      let leg = self.plan_leg(
+        (leg[0].longitude, leg[0].latitude),
+        (leg[1].longitude, leg[1].latitude),
+      )?;
+      let skip = if locations.is_empty() { 0 } else { 1 };
+      locations.extend(leg.into_iter().skip(skip));
+    }
+    let length = locations
+      .windows(2)
+      .map(|pair| distance(pair[0], pair[1]))
+      .sum::<f64>();
+    let speed = if self.walk_mode == params.mode {
+      WALK_SPEED
+    } else {
+      DRIVE_SPEED
+    };
+
+    let route_id = routing_engine::route_id(params)?;
+
+    let mut describe = RoadDescribe::new();
+    describe.routeID = route_id;
+    describe.mode = params.mode;
+    describe.policy = params.policy;
+    describe.length = length.round() as u32;
+    describe.time = (length / speed).round() as u32;
+    describe.routeStartPoint = SingularPtrField::some(start.clone());
+    describe.routeEndPoint = SingularPtrField::some(end);
+
+    let mut form = RoadForm::new();
+    form.fromIndex = 0;
+    form.toIndex = (locations.len() - 1) as u32;
+    form.modelID = start.modelID;
+    form.floor = start.floor;
+
+    let mut route = Route::new();
+    route.describe = SingularPtrField::some(describe);
+    route.roadPoints = locations
+      .iter()
+      .map(|location| routing_engine::road_point(*location))
+      .collect();
+    route.roadForms = RepeatedField::from_vec(vec![form]);
+
+    let mut result = RouteResult::new();
+    result.version = params.version;
+    result.routes = RepeatedField::from_vec(vec![route]);
+    Ok((route_id.to_string(), result))
+  }
+}
+
+#[cfg(test)]
+pub mod tests {
+  use super::*;
+  use crate::protos::route_common::GeoPoint;
+
+  fn make_point(longitude: f64, latitude: f64) -> GeoPoint {
+    let mut point = GeoPoint::new();
+    point.longitude = longitude;
+    point.latitude = latitude;
+    point
+  }
+
+  fn make_params(start: GeoPoint, end: GeoPoint) -> RoutePlanServerParameter {
+    let mut params = RoutePlanServerParameter::new();
+    params.mode = DEFAULT_WALK_MODE;
+    params.set_startPoint(start);
+    params.set_endPoint(end);
+    params
+  }
+
+  fn fixture_engine() -> MockEngine {
+    let graph = MockGraph::from_json(include_str!("../../../fixtures/mock_graph.json")).unwrap();
+    MockEngine::new(Some(graph))
+  }
+
+  #[test]
+  fn plans_straight_lines() {
+    let engine = MockEngine::default();
+    let params = make_params(make_point(116.447, 39.912), make_point(116.448, 39.912));
+
+    let (id, result) = engine.find_path(&params).unwrap();
+    let route = &result.routes[0];
+    assert_eq!(route.get_describe().routeID.to_string(), id);
+    assert_eq!(route.get_describe().length, 85);
+    assert_eq!(route.get_describe().time, 71);
+    assert_eq!(route.roadPoints.len(), 2);
+    assert_eq!(route.roadForms[0].toIndex, 1);
+
+    assert_eq!(engine.find_path(&params).unwrap().0, id);
+    assert_eq!(
+      engine.find_native_path(&params),
+      Err(RouteError::Unsupported("native route format"))
+    );
+  }
+
+  #[test]
+  fn plans_on_the_fixture_graph() {
+    let engine = fixture_engine();
+    let params = make_params(make_point(116.4469, 39.9119), make_point(116.4469, 39.9131));
+
+    let (_, result) = engine.find_path(&params).unwrap();
+    let points = result.routes[0]
+      .roadPoints
+      .iter()
+      .map(|point| (point.longitude, point.latitude))
+      .collect::<Vec<(f64, f64)>>();
+    assert_eq!(
+      points,
+      vec![
+        (116.4469, 39.9119),
+        (116.447, 39.912),
+        (116.448, 39.912),
+        (116.448, 39.913),
+        (116.447, 39.913),
+        (116.4469, 39.9131),
+      ]
+    );
+  }
+
+  #[test]
+  fn reports_unreachable_points() {
+    let engine = fixture_engine();
+    let params = make_params(make_point(116.447, 39.912), make_point(116.46, 39.92));
+    assert_eq!(
+      engine.find_path(&params).map(|_| ()),
+      Err(RouteError::NoRoute)
+    );
+
+    let mut params = RoutePlanServerParameter::new();
+    params.set_endPoint(make_point(116.46, 39.92));
+    assert!(matches!(
+      engine.find_path(&params),
+      Err(RouteError::InvalidParameter(_))
+    ));
+  }
+
+  #[test]
+  fn walks_in_the_configured_mode() {
+    let config = EngineConfig {
+      walk_mode: 4,
+      ..Default::default()
+    };
+    let engine = MockEngine::from_config(&config).unwrap();
+    let mut params = make_params(make_point(116.447, 39.912), make_point(116.448, 39.912));
+
+    let (_, result) = engine.find_path(&params).unwrap();
+    assert_eq!(result.routes[0].get_describe().time, 9);
+
+    params.mode = 4;
+    let (_, result) = engine.find_path(&params).unwrap();
+    assert_eq!(result.routes[0].get_describe().time, 71);
+  }
+
+  #[test]
+  fn reports_unreadable_graphs() {
+    let mut config = EngineConfig::default();
+    config.kind = String::from("mock");
+    assert!(MockEngine::from_config(&config).is_ok());
+
+    config.graph = String::from("fixtures/missing_graph.json");
+    let error = MockEngine::from_config(&config).unwrap_err();
+    assert!(error.starts_with("mock engine graph fixtures/missing_graph.json: "));
+    assert!(routing_engine::make_engine(&config).is_err());
+  }
+}
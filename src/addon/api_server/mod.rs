@@ -1,197 +1,138 @@
-use anyhow::Result;
-use http::response::Builder as HttpResponseBuilder;
-use http::StatusCode;
-use hyper::{body::HttpBody, Body, Method, Request, Response};
+use http::header::HeaderValue;
+use hyper::{Body, Request, Response};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
 use crate::config::Config;
+use crate::utils::trace::{
+  make_trace_id, UpstreamTraceIds, TRACE_ID_HEADER, UPSTREAM_TRACE_ID_HEADER,
+};
 
+use self::error::ApiError;
+use self::router::{RouteMatch, RouteRequest, Router};
+
+mod body;
+mod endpoint;
+mod error;
+mod floor_legs;
+mod guidance;
+mod http_client;
+mod memory_cache;
+mod mock_engine;
+mod poi_cache;
+mod poi_info;
+mod proto_json;
 mod proto_wrapper;
 mod redis_client;
+mod road_graph;
+mod route_cache;
+mod route_export;
+mod route_format;
+mod route_planner;
+#[cfg(feature = "native-engine")]
 mod route_wrapper;
+mod router;
+mod routing_engine;
 mod search_poi;
 mod service;
+mod validation;
+mod walk_engine;
 
 #[derive(Default, Debug)]
 pub struct ApiServer {
   services: Arc<service::Service>,
+  router: Router<service::Service>,
 }
 
 impl<'a> ApiServer {
-  /// Creates a new instance of the `FileExplorer` with the provided `root_dir`
-  pub fn new(config: Arc<Config>) -> Self {
-    ApiServer {
-      services: Arc::new(service::Service::new(Arc::clone(&config))),
-    }
+  /// Creates a new instance of the `ApiServer` with the routes registered
+  /// by `service::Service`, fails when the services can't be created
+  pub fn new(config: Arc<Config>) -> Result<Self, String> {
+    let mut router = Router::new();
+    service::Service::register_routes(&mut router);
+
+    Ok(ApiServer {
+      services: Arc::new(service::Service::new(Arc::clone(&config))?),
+      router,
+    })
   }
 
   /// Resolves a HTTP Request to a api.
-  pub async fn resolve(
-    &self,
-    request: Arc<Mutex<Request<Body>>>,
-  ) -> Result<Response<Body>, StatusCode> {
-    let mut request_lock = request.lock().await;
-    let mut req_path = request_lock.uri().to_string();
-    let req_method = request_lock.method();
-    println!("{:?}{:?}", req_path.to_string(), req_method.to_string());
-    match *req_method {
-      Method::GET => {
-        if req_path.contains("/api/v1/health") {
-          return self.health();
-        } else if req_path.contains("/api/v1/navi") {
-          let pos = req_path.find(':');
-          match pos {
-            Some(pos) => {
-              let id = req_path.split_off(pos + 1);
-              return self.get_id(id);
-            }
-            None => {
-              return Err(StatusCode::CONTINUE);
-            }
-          }
-        } else {
-          return Err(StatusCode::CONTINUE);
-        }
-      }
-      Method::POST => {
-        if req_path.contains("/api/v1/navijson") {
-          let orb = request_lock.body_mut().data().await;
-          match orb {
-            Some(result) => match result {
-              Ok(bytes) => {
-                let body_data = String::from_utf8(bytes.to_vec()).expect("body_data");
-                println!("{:?}", body_data);
-                return self.get_path_from_json(body_data).await;
-              }
-              Err(error) => {
-                println!("search_poi_info->{:?}", error);
-                return Err(StatusCode::CONTINUE);
-              }
-            },
-            None => {
-              println!("search_poi_info->None");
-              return Err(StatusCode::CONTINUE);
-            }
-          }
-        } else if req_path.contains("/api/v1/navi") {
-          let orb = request_lock.body_mut().data().await;
-          match orb {
-            Some(result) => match result {
-              Ok(bytes) => {
-                let body_data = String::from_utf8(bytes.to_vec()).expect("body_data");
-                println!("{:?}", body_data);
-                return self.get_path(body_data).await;
-              }
-              Err(error) => {
-                println!("search_poi_info->{:?}", error);
-                return Err(StatusCode::CONTINUE);
-              }
-            },
-            None => {
-              println!("search_poi_info->None");
-              return Err(StatusCode::CONTINUE);
-            }
-          }
-        } else {
-          return Err(StatusCode::CONTINUE);
-        }
-      }
-      _ => return Err(StatusCode::CONTINUE),
-    }
-  }
-}
+  ///
+  /// Every response carries the request trace id in the `X-Trace-Id`
+  /// header, the trace id is taken from the request when provided. The
+  /// trace ids given by the POI service are listed in the
+  /// `X-Upstream-Trace-Id` header.
+  ///
+  /// Failures are responded as a JSON error body, refer to `ApiError` for
+  /// the HTTP status of each failure
+  pub async fn resolve(&self, request: Arc<Mutex<Request<Body>>>) -> Response<Body> {
+    let (req_method, req_path, trace_id) = {
+      let request_lock = request.lock().await;
+      let trace_id = request_lock
+        .headers()
+        .get(TRACE_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(String::from)
+        .unwrap_or_else(make_trace_id);
+      (
+        request_lock.method().clone(),
+        String::from(request_lock.uri().path()),
+        trace_id,
+      )
+    };
+    println!("[{}] {} {}", trace_id, req_method, req_path);
+    let upstream_trace_ids = Arc::new(UpstreamTraceIds::default());
 
-impl ApiServer {
-  fn health(&self) -> Result<Response<Body>, StatusCode> {
-    Ok(
-      HttpResponseBuilder::new()
-        .header(http::header::CONTENT_TYPE, "text/html")
-        .status(StatusCode::OK)
-        .body(Body::from("health"))
-        .expect("Failed to build response"),
-    )
-  }
-  fn get_id(&self, id: String) -> Result<Response<Body>, StatusCode> {
-    let mut services_arc = self.services.clone();
-    let services_mut = Arc::make_mut(&mut services_arc);
-    match services_mut.get_value(&id) {
-      Some(ret) => Ok(
-        HttpResponseBuilder::new()
-          .header(http::header::CONTENT_TYPE, "application/octet-stream")
-          .status(StatusCode::OK)
-          .body(Body::from(ret))
-          .expect("Failed to build response"),
-      ),
-      None => {
-        println!("get_id>ret=");
-        Err(StatusCode::INTERNAL_SERVER_ERROR)
-      }
-    }
-  }
-  async fn get_path(&self, raw_data: String) -> Result<Response<Body>, StatusCode> {
-    let result = self.services.find_path(raw_data).await;
-    match result {
-      Ok(ret) => {
-        // let mut services_arc = self.services.clone();
-        // let services_mut = Arc::make_mut(&mut services_arc);
-        // let redis_result = services_mut.save_value(&ret.0, &ret.1);
-        // match redis_result {
-        //   Ok(_) => {},
-        //   Err(error) => {
-        //     println!("get_path>ret={}", error);
-        //   }
-        // }
-        Ok(
-          HttpResponseBuilder::new()
-            .header(http::header::CONTENT_TYPE, "text/html")
-            .status(StatusCode::OK)
-            .body(Body::from(ret.1))
-            .expect("Failed to build response"),
+    let result = match self.router.route(&req_method, &req_path) {
+      RouteMatch::Found(handler, params) => {
+        handler(
+          Arc::clone(&self.services),
+          RouteRequest {
+            request,
+            params,
+            trace_id: trace_id.clone(),
+            upstream_trace_ids: Arc::clone(&upstream_trace_ids),
+          },
         )
+        .await
       }
-      Err(error) => {
-        println!("get_id>ret={}", error);
-        Err(StatusCode::INTERNAL_SERVER_ERROR)
-      }
-    }
-  }
-  async fn get_path_from_json(&self, json_data: String) -> Result<Response<Body>, StatusCode> {
-    let result = self.services.find_path_from_json(json_data).await;
-    match result {
-      Ok(ret) => {
-        // let mut services_arc = self.services.clone();
-        // let services_mut = Arc::make_mut(&mut services_arc);
-        // let redis_result = services_mut.save_value(&ret.0, &ret.1);
-        // match redis_result {
-        //   Ok(_) => {},
-        //   Err(error) => {
-        //     println!("get_path>ret={}", error);
-        //   }
-        // }
-        Ok(
-          HttpResponseBuilder::new()
-            .header(http::header::CONTENT_TYPE, "text/html")
-            .status(StatusCode::OK)
-            .body(Body::from(ret.1))
-            .expect("Failed to build response"),
-        )
+      RouteMatch::MethodNotAllowed(allowed) => Err(ApiError::MethodNotAllowed(allowed)),
+      RouteMatch::NotFound => Err(ApiError::NotFound),
+    };
+
+    let mut response = match result {
+      Ok(mut response) => {
+        if let Ok(value) = HeaderValue::from_str(&trace_id) {
+          response.headers_mut().insert(TRACE_ID_HEADER, value);
+        }
+        response
       }
       Err(error) => {
-        println!("get_id>ret={}", error);
-        Err(StatusCode::INTERNAL_SERVER_ERROR)
+        println!("[{}]resolve->{}", trace_id, error);
+        error.into_response(&trace_id)
+      }
+    };
+    if let Some(upstream) = upstream_trace_ids.header_value() {
+      println!("[{}]resolve->upstream {}", trace_id, upstream);
+      if let Ok(value) = HeaderValue::from_str(&upstream) {
+        response
+          .headers_mut()
+          .insert(UPSTREAM_TRACE_ID_HEADER, value);
       }
     }
+    response
   }
 }
 
-#[cfg(test)]
+// runs the native routing library
+#[cfg(all(test, feature = "native-engine"))]
 pub mod tests {
 
   use protobuf::{Message, SingularPtrField};
 
   use crate::{
-    addon::api_server::route_wrapper::RouteWrapper,
+    addon::api_server::route_wrapper::{RouteWrapper, PROTOBUF_ROUTE_FORMAT},
     config::ServerType,
     protos::{route_client_param::RoutePlanClientParameter, route_common::GeoPoint},
   };
@@ -201,7 +142,7 @@ pub mod tests {
     use super::*;
     let mut config = Config::default();
     config.action = ServerType::ApiServices;
-    let server = Arc::new(ApiServer::new(Arc::from(config)));
+    let server = Arc::new(ApiServer::new(Arc::from(config)).unwrap());
     let mut client_params = RoutePlanClientParameter::new();
     client_params.mode = 0;
     client_params.policy = 0;
@@ -209,7 +150,7 @@ pub mod tests {
     let mut start_point = GeoPoint::new();
     start_point.longitude = 116.447209f64;
     start_point.latitude = 39.912554f64;
-    start_point.height = 0i32;
+    start_point.height = -1i32;
     // start_point.modelID = 0u32;
     // start_point.floor = 0i32;
     let mut end_point = GeoPoint::new();
@@ -221,21 +162,23 @@ pub mod tests {
     client_params.startPoint = SingularPtrField::some(start_point);
     client_params.endPoint = SingularPtrField::some(end_point);
     let output = client_params.write_to_bytes().expect("output");
-    let output_string = String::from_utf8(output).expect("output_string");
+    // a negative height is encoded as 0xff bytes, the request is not valid UTF-8
+    assert!(String::from_utf8(output.clone()).is_err());
     tokio::task::spawn_blocking(move || {
       // do some compute-heavy work or call synchronous code
       let local_server = server.clone();
       async move {
-        let local_output_string = output_string;
+        let local_output = output;
         let local_local_server = local_server.clone();
         let encode_data = proto_wrapper::client_to_server_protobuf(
-          &local_output_string,
+          &local_output,
           &*local_local_server.services,
+          &UpstreamTraceIds::default(),
         )
         .await;
         match encode_data {
           Ok(encoded) => {
-            let ret = RouteWrapper::find_path(encoded);
+            let ret = RouteWrapper::find_path(&encoded, PROTOBUF_ROUTE_FORMAT);
             // let ret = local_server.get_path(output_string).await;
             match ret {
               Ok(content) => {
@@ -253,6 +196,4 @@ pub mod tests {
       }
     });
   }
-
-  
 }
@@ -0,0 +1,236 @@
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::config::PoiCacheConfig;
+use crate::utils::trace::UpstreamTraceIds;
+
+use super::memory_cache::MemoryKvCache;
+use super::route_cache::KvCache;
+use super::search_poi::{PoiDetail, SearchError, SearchPoiInfo};
+
+/// Prefix of the keys of POI details
+const POI_KEY_PREFIX: &str = "navi:poi:";
+
+/// Counters of the POI cache lookups since the server started
+#[derive(Debug, Default)]
+pub struct PoiCacheMetrics {
+  /// Lookups answered with a cached POI detail
+  hits: AtomicU64,
+  /// Lookups answered with a cached unknown POI id
+  negative_hits: AtomicU64,
+  /// Lookups sent to the POI service
+  misses: AtomicU64,
+  /// Cache reads and writes which failed
+  errors: AtomicU64,
+}
+
+impl PoiCacheMetrics {
+  pub fn to_json(&self) -> Value {
+    json!({
+      "hits": self.hits.load(Ordering::Relaxed),
+      "negativeHits": self.negative_hits.load(Ordering::Relaxed),
+      "misses": self.misses.load(Ordering::Relaxed),
+      "errors": self.errors.load(Ordering::Relaxed),
+    })
+  }
+
+  fn count(counter: &AtomicU64) {
+    counter.fetch_add(1, Ordering::Relaxed);
+  }
+}
+
+/// POI details from the POI service cached by POI id. Ids unknown to the
+/// POI service are cached as well, for `negative_ttl`, while upstream
+/// failures are never cached
+#[derive(Debug, Clone)]
+pub struct PoiCache {
+  cache: Arc<dyn KvCache>,
+  config: PoiCacheConfig,
+  metrics: Arc<PoiCacheMetrics>,
+}
+
+/// Value stored for an id the POI service does not know
+const NOT_FOUND: &[u8] = b"";
+
+impl Default for PoiCache {
+  fn default() -> Self {
+    let config = PoiCacheConfig::default();
+    PoiCache::new(Arc::new(MemoryKvCache::new(config.max_entries, 0)), config)
+  }
+}
+
+impl PoiCache {
+  pub fn new(cache: Arc<dyn KvCache>, config: PoiCacheConfig) -> PoiCache {
+    PoiCache {
+      cache,
+      config,
+      metrics: Arc::new(PoiCacheMetrics::default()),
+    }
+  }
+
+  pub fn metrics(&self) -> &PoiCacheMetrics {
+    &self.metrics
+  }
+
+  /// The POI detail of `id`, from the cache when possible, otherwise from
+  /// `search_poi_info`. Cache failures are counted and fall back to the
+  /// POI service
+  pub async fn search(
+    &self,
+    search_poi_info: &SearchPoiInfo,
+    id: &str,
+    upstream_trace_ids: &UpstreamTraceIds,
+  ) -> Result<PoiDetail, SearchError> {
+    if 0 == self.config.ttl {
+      return search_poi_info
+        .search_poi_info(id, upstream_trace_ids)
+        .await;
+    }
+    let key = format!("{}{}", POI_KEY_PREFIX, id);
+    match self.cache.get(&key).await {
+      Ok(Some(value)) if NOT_FOUND == value.as_slice() => {
+        PoiCacheMetrics::count(&self.metrics.negative_hits);
+        return Err(SearchError::NotFound(id.to_string()));
+      }
+      Ok(Some(value)) => match serde_json::from_slice::<PoiDetail>(&value) {
+        Ok(detail) => {
+          PoiCacheMetrics::count(&self.metrics.hits);
+          return Ok(detail);
+        }
+        Err(error) => {
+          PoiCacheMetrics::count(&self.metrics.errors);
+          println!("PoiCache::search[{}]->{}", id, error);
+        }
+      },
+      Ok(None) => (),
+      Err(error) => {
+        PoiCacheMetrics::count(&self.metrics.errors);
+        println!("PoiCache::search[{}]->{}", id, error);
+      }
+    }
+
+    PoiCacheMetrics::count(&self.metrics.misses);
+    let detail = search_poi_info
+      .search_poi_info(id, upstream_trace_ids)
+      .await;
+    let stored = match &detail {
+      Ok(detail) => match serde_json::to_vec(detail) {
+        Ok(value) => self.cache.set(&key, &value, self.config.ttl).await,
+        Err(_) => Ok(()),
+      },
+      Err(SearchError::NotFound(_)) if 0 < self.config.negative_ttl => {
+        self
+          .cache
+          .set(&key, NOT_FOUND, self.config.negative_ttl)
+          .await
+      }
+      Err(_) => Ok(()),
+    };
+    if let Err(error) = stored {
+      PoiCacheMetrics::count(&self.metrics.errors);
+      println!("PoiCache::search[{}]->{}", id, error);
+    }
+    detail
+  }
+}
+
+#[cfg(test)]
+pub mod tests {
+  use super::*;
+
+  fn make_detail(id: &str) -> PoiDetail {
+    serde_json::from_value(json!({
+      "addressFloor": "1",
+      "buildFlag": "0",
+      "latitude": "39.912554",
+      "longitude": "116.447209",
+      "modelId": "0",
+      "parentPoiId": "",
+      "poiId": id,
+      "poiName": "entrance",
+      "poiType": "",
+      "roadId": "0",
+      "roadXEntr": "",
+      "roadYEntr": "",
+      "wroadId": "",
+      "wroadXEntr": "",
+      "wroadYEntr": "",
+    }))
+    .unwrap()
+  }
+
+  fn make_poi_cache() -> PoiCache {
+    PoiCache::new(
+      Arc::new(MemoryKvCache::new(0, 0)),
+      PoiCacheConfig::default(),
+    )
+  }
+
+  // the POI service is never reached by these tests
+  fn make_search_poi_info() -> SearchPoiInfo {
+    SearchPoiInfo::new(String::from("http://127.0.0.1:9/"), Default::default())
+  }
+
+  #[tokio::test]
+  async fn serves_cached_details() {
+    let poi_cache = make_poi_cache();
+    let detail = serde_json::to_vec(&make_detail("21")).unwrap();
+    poi_cache
+      .cache
+      .set("navi:poi:21", &detail, 60)
+      .await
+      .unwrap();
+
+    let ret = poi_cache
+      .search(
+        &make_search_poi_info(),
+        &String::from("21"),
+        &Default::default(),
+      )
+      .await
+      .unwrap();
+
+    assert_eq!(ret.poiId, "21");
+    assert_eq!(poi_cache.metrics().to_json()["hits"], 1);
+    assert_eq!(poi_cache.metrics().to_json()["misses"], 0);
+  }
+
+  #[tokio::test]
+  async fn serves_cached_unknown_ids() {
+    let poi_cache = make_poi_cache();
+    poi_cache
+      .cache
+      .set("navi:poi:404", NOT_FOUND, 60)
+      .await
+      .unwrap();
+
+    let ret = poi_cache
+      .search(
+        &make_search_poi_info(),
+        &String::from("404"),
+        &Default::default(),
+      )
+      .await;
+
+    assert!(matches!(ret, Err(SearchError::NotFound(_))));
+    assert_eq!(poi_cache.metrics().to_json()["negativeHits"], 1);
+  }
+
+  #[tokio::test]
+  async fn does_not_cache_upstream_failures() {
+    let poi_cache = make_poi_cache();
+
+    let ret = poi_cache
+      .search(
+        &make_search_poi_info(),
+        &String::from("21"),
+        &Default::default(),
+      )
+      .await;
+
+    assert!(matches!(ret, Err(SearchError::Upstream(_))));
+    assert_eq!(poi_cache.metrics().to_json()["misses"], 1);
+    assert_eq!(poi_cache.cache.get("navi:poi:21").await, Ok(None));
+  }
+}
@@ -0,0 +1,275 @@
+use protobuf::SingularPtrField;
+use std::error::Error;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::protos::route_common::GeoPoint;
+use crate::protos::route_server_param::PoiInfo;
+
+use super::search_poi::PoiDetail;
+
+/// A `PoiDetail` which can't be routed to
+#[derive(Debug, Clone, PartialEq)]
+pub enum PoiDetailError {
+  /// A field is not a number of the expected type
+  InvalidField {
+    poi_id: String,
+    field: &'static str,
+    value: String,
+  },
+  /// A coordinate is out of the range of its axis
+  OutOfRange {
+    poi_id: String,
+    field: &'static str,
+    value: f64,
+  },
+  /// Only one coordinate of the entry is set
+  PartialEntry { poi_id: String, field: &'static str },
+  /// Neither the entry for the route mode nor the POI location is set
+  MissingLocation(String),
+}
+
+impl Error for PoiDetailError {}
+
+impl Display for PoiDetailError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      PoiDetailError::InvalidField {
+        poi_id,
+        field,
+        value,
+      } => write!(f, "poi {}: invalid {}: {:?}", poi_id, field, value),
+      PoiDetailError::OutOfRange {
+        poi_id,
+        field,
+        value,
+      } => write!(f, "poi {}: {} out of range: {}", poi_id, field, value),
+      PoiDetailError::PartialEntry { poi_id, field } => {
+        write!(f, "poi {}: missing {}", poi_id, field)
+      }
+      PoiDetailError::MissingLocation(poi_id) => write!(f, "poi {}: no location", poi_id),
+    }
+  }
+}
+
+/// Parses a numeric field of `detail`, `None` when it is empty
+fn parse_field<T: FromStr>(
+  detail: &PoiDetail,
+  field: &'static str,
+  value: &str,
+) -> Result<Option<T>, PoiDetailError> {
+  let value = value.trim();
+  if value.is_empty() {
+    return Ok(None);
+  }
+  value
+    .parse::<T>()
+    .map(Some)
+    .map_err(|_| PoiDetailError::InvalidField {
+      poi_id: detail.poiId.clone(),
+      field,
+      value: String::from(value),
+    })
+}
+
+/// Parses a decimal degree coordinate no greater than `limit` in absolute
+/// value
+fn parse_coordinate(
+  detail: &PoiDetail,
+  field: &'static str,
+  value: &str,
+  limit: f64,
+) -> Result<Option<f64>, PoiDetailError> {
+  match parse_field::<f64>(detail, field, value)? {
+    Some(degrees) if !degrees.is_finite() || degrees.abs() > limit => {
+      Err(PoiDetailError::OutOfRange {
+        poi_id: detail.poiId.clone(),
+        field,
+        value: degrees,
+      })
+    }
+    degrees => Ok(degrees),
+  }
+}
+
+/// The `(longitude, latitude)` of a pair of coordinate fields, `None` when
+/// both are empty
+fn parse_location(
+  detail: &PoiDetail,
+  (x_field, x): (&'static str, &str),
+  (y_field, y): (&'static str, &str),
+) -> Result<Option<(f64, f64)>, PoiDetailError> {
+  let longitude = parse_coordinate(detail, x_field, x, 180.0)?;
+  let latitude = parse_coordinate(detail, y_field, y, 90.0)?;
+  match (longitude, latitude) {
+    (Some(longitude), Some(latitude)) => Ok(Some((longitude, latitude))),
+    (None, None) => Ok(None),
+    (Some(_), None) => Err(PoiDetailError::PartialEntry {
+      poi_id: detail.poiId.clone(),
+      field: y_field,
+    }),
+    (None, Some(_)) => Err(PoiDetailError::PartialEntry {
+      poi_id: detail.poiId.clone(),
+      field: x_field,
+    }),
+  }
+}
+
+/// Converts a `PoiDetail` of the POI service into the `PoiInfo` of a route
+/// request.
+///
+/// The route enters the POI at `wroadXEntr`/`wroadYEntr` on `wroadId`
+/// when `walk` and at `roadXEntr`/`roadYEntr` on `roadId` otherwise,
+/// falling back to the POI location when the entry is not set. Empty
+/// integer fields are `0`
+pub fn to_poi_info(detail: &PoiDetail, walk: bool) -> Result<PoiInfo, PoiDetailError> {
+  let (road_id, entry) = if walk {
+    (
+      ("wroadId", detail.wroadId.as_str()),
+      parse_location(
+        detail,
+        ("wroadXEntr", &detail.wroadXEntr),
+        ("wroadYEntr", &detail.wroadYEntr),
+      )?,
+    )
+  } else {
+    (
+      ("roadId", detail.roadId.as_str()),
+      parse_location(
+        detail,
+        ("roadXEntr", &detail.roadXEntr),
+        ("roadYEntr", &detail.roadYEntr),
+      )?,
+    )
+  };
+  let entry = match entry {
+    Some(entry) => entry,
+    None => parse_location(
+      detail,
+      ("longitude", &detail.longitude),
+      ("latitude", &detail.latitude),
+    )?
+    .ok_or_else(|| PoiDetailError::MissingLocation(detail.poiId.clone()))?,
+  };
+  let model_id = parse_field::<u32>(detail, "modelId", &detail.modelId)?.unwrap_or_default();
+
+  let mut point = GeoPoint::new();
+  point.longitude = entry.0;
+  point.latitude = entry.1;
+  point.height = 0;
+  point.modelID = model_id;
+  point.floor =
+    parse_field::<i32>(detail, "addressFloor", &detail.addressFloor)?.unwrap_or_default();
+
+  let mut ret = PoiInfo::new();
+  ret.poiID = parse_field::<u64>(detail, "poiId", &detail.poiId)?.unwrap_or_default();
+  ret.poiName = detail.poiName.clone();
+  ret.roadID = parse_field::<u64>(detail, road_id.0, road_id.1)?.unwrap_or_default();
+  ret.entry = SingularPtrField::some(point);
+  ret.modelID = model_id as u64;
+  Ok(ret)
+}
+
+#[cfg(test)]
+pub mod tests {
+  use super::*;
+  use serde_json::json;
+
+  fn make_detail() -> PoiDetail {
+    serde_json::from_value(json!({
+      "addressFloor": "-1",
+      "buildFlag": "0",
+      "latitude": "39.912554",
+      "longitude": "116.447209",
+      "modelId": "3",
+      "parentPoiId": "",
+      "poiId": "21",
+      "poiName": "entrance",
+      "poiType": "",
+      "roadId": "1001",
+      "roadXEntr": "116.4475",
+      "roadYEntr": "39.9127",
+      "wroadId": "2002",
+      "wroadXEntr": " 116.4473 ",
+      "wroadYEntr": "39.9126",
+    }))
+    .unwrap()
+  }
+
+  #[test]
+  fn enters_by_mode() {
+    let detail = make_detail();
+
+    let walk = to_poi_info(&detail, true).unwrap();
+    assert_eq!(walk.poiID, 21);
+    assert_eq!(walk.roadID, 2002);
+    assert_eq!(walk.modelID, 3);
+    assert_eq!(walk.get_entry().longitude, 116.4473);
+    assert_eq!(walk.get_entry().latitude, 39.9126);
+    assert_eq!(walk.get_entry().floor, -1);
+
+    let drive = to_poi_info(&detail, false).unwrap();
+    assert_eq!(drive.roadID, 1001);
+    assert_eq!(drive.get_entry().longitude, 116.4475);
+    assert_eq!(drive.get_entry().latitude, 39.9127);
+  }
+
+  #[test]
+  fn falls_back_to_location() {
+    let mut detail = make_detail();
+    detail.wroadXEntr = String::new();
+    detail.wroadYEntr = String::new();
+    detail.wroadId = String::new();
+    detail.modelId = String::new();
+    detail.addressFloor = String::new();
+
+    let ret = to_poi_info(&detail, true).unwrap();
+    assert_eq!(ret.roadID, 0);
+    assert_eq!(ret.modelID, 0);
+    assert_eq!(ret.get_entry().longitude, 116.447209);
+    assert_eq!(ret.get_entry().latitude, 39.912554);
+    assert_eq!(ret.get_entry().floor, 0);
+
+    detail.longitude = String::new();
+    detail.latitude = String::new();
+    assert_eq!(
+      to_poi_info(&detail, true),
+      Err(PoiDetailError::MissingLocation(String::from("21")))
+    );
+  }
+
+  #[test]
+  fn reports_invalid_fields() {
+    let mut detail = make_detail();
+    detail.roadId = String::from("road");
+    assert_eq!(
+      to_poi_info(&detail, false),
+      Err(PoiDetailError::InvalidField {
+        poi_id: String::from("21"),
+        field: "roadId",
+        value: String::from("road"),
+      })
+    );
+
+    let mut detail = make_detail();
+    detail.roadYEntr = String::from("139.9");
+    assert_eq!(
+      to_poi_info(&detail, false),
+      Err(PoiDetailError::OutOfRange {
+        poi_id: String::from("21"),
+        field: "roadYEntr",
+        value: 139.9,
+      })
+    );
+
+    let mut detail = make_detail();
+    detail.roadXEntr = String::new();
+    assert_eq!(
+      to_poi_info(&detail, false),
+      Err(PoiDetailError::PartialEntry {
+        poi_id: String::from("21"),
+        field: "roadXEntr",
+      })
+    );
+  }
+}
@@ -0,0 +1,89 @@
+use protobuf::reflect::{ReflectFieldRef, ReflectValueRef};
+use protobuf::Message;
+use serde_json::{json, Map, Value};
+
+const BASE64_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64(bytes: &[u8]) -> String {
+  let mut encoded = String::new();
+  for chunk in bytes.chunks(3) {
+    let bits = chunk.iter().enumerate().fold(0u32, |bits, (index, byte)| {
+      bits | (*byte as u32) << (16 - 8 * index)
+    });
+    for index in 0..4 {
+      if index <= chunk.len() {
+        encoded.push(BASE64_ALPHABET[(bits >> (18 - 6 * index) & 0x3f) as usize] as char);
+      } else {
+        encoded.push('=');
+      }
+    }
+  }
+  encoded
+}
+
+fn value_json(value: ReflectValueRef) -> Value {
+  match value {
+    ReflectValueRef::U32(value) => json!(value),
+    // 64-bit integers are strings in the canonical JSON mapping
+    ReflectValueRef::U64(value) => json!(value.to_string()),
+    ReflectValueRef::I32(value) => json!(value),
+    ReflectValueRef::I64(value) => json!(value.to_string()),
+    ReflectValueRef::F32(value) => json!(value),
+    ReflectValueRef::F64(value) => json!(value),
+    ReflectValueRef::Bool(value) => json!(value),
+    ReflectValueRef::String(value) => json!(value),
+    ReflectValueRef::Bytes(value) => json!(base64(value)),
+    ReflectValueRef::Enum(value) => json!(value.name()),
+    ReflectValueRef::Message(message) => to_json(message),
+  }
+}
+
+/// Exports a message in the protobuf canonical JSON mapping, fields
+/// holding their default value are left out
+pub fn to_json(message: &dyn Message) -> Value {
+  let mut object = Map::new();
+  for field in message.descriptor().fields() {
+    let value = match field.get_reflect(message) {
+      ReflectFieldRef::Optional(Some(value)) if value.is_non_zero() => value_json(value),
+      ReflectFieldRef::Repeated(values) if 0 < values.len() => Value::Array(
+        values
+          .reflect_iter()
+          .map(|value| value_json(value.as_ref()))
+          .collect(),
+      ),
+      // no message of the API has a map field
+      _ => continue,
+    };
+    object.insert(String::from(field.json_name()), value);
+  }
+  Value::Object(object)
+}
+
+#[cfg(test)]
+pub mod tests {
+  use super::*;
+  use crate::protos::route_result::{Route, RouteResult};
+
+  #[test]
+  fn encodes_base64_with_padding() {
+    assert_eq!(base64(b""), "");
+    assert_eq!(base64(b"f"), "Zg==");
+    assert_eq!(base64(b"fo"), "Zm8=");
+    assert_eq!(base64(b"foo"), "Zm9v");
+    assert_eq!(base64(&[0xfb, 0xff]), "+/8=");
+  }
+
+  #[test]
+  fn follows_canonical_json_mapping() {
+    let mut route = Route::new();
+    route.mut_describe().routeID = 42;
+    route.mut_describe().length = 120;
+    let mut route_result = RouteResult::new();
+    route_result.routes.push(route);
+
+    assert_eq!(
+      to_json(&route_result),
+      json!({ "routes": [{ "describe": { "routeID": "42", "length": 120 } }] })
+    );
+  }
+}
@@ -2,135 +2,622 @@
 
 use crate::protos::{
   route_client_param::RoutePlanClientParameter,
-  route_common::GeoPoint,
+  route_common::{GeoArea, GeoPoint},
   route_server_param::{
     RoutePlanServerParameter, RoutePlanServerParameter_oneof_end,
     RoutePlanServerParameter_oneof_start,
   },
 };
-use protobuf::Message;
-use std::error::Error;
+use protobuf::reflect::MessageDescriptor;
+use protobuf::{Message, RepeatedField, SingularPtrField};
+use serde_json::Value;
 
+use super::error::ApiError;
 use super::service;
+use super::validation::{validate_client_params, ValidationErrors};
+use crate::utils::trace::UpstreamTraceIds;
 
 pub async fn client_to_server_protobuf(
-  data: &String,
+  data: &[u8],
   services: &service::Service,
-) -> Result<Vec<u8>, Box<dyn Error>> {
-  let client_params = RoutePlanClientParameter::parse_from_bytes(data.as_bytes()).unwrap();
-  let start_point_info = services.get_poi_info(&client_params.startPoiID).await;
-  let end_point_info = services.get_poi_info(&client_params.endPoiID).await;
+  upstream_trace_ids: &UpstreamTraceIds,
+) -> Result<Vec<u8>, ApiError> {
+  let client_params = RoutePlanClientParameter::parse_from_bytes(data).map_err(|error| {
+    let mut errors = ValidationErrors::new();
+    errors.add("body", &error.to_string());
+    errors
+  })?;
+  validate_client_params(&client_params)?;
+  let server_params = client_to_server_params(client_params, services, upstream_trace_ids).await?;
+  encode(&server_params)
+}
+
+pub async fn client_json_to_server_protobuf(
+  data: &str,
+  services: &service::Service,
+  upstream_trace_ids: &UpstreamTraceIds,
+) -> Result<Vec<u8>, ApiError> {
+  let client_params = client_params_from_json(data)?;
+  validate_client_params(&client_params)?;
+  let server_params = client_to_server_params(client_params, services, upstream_trace_ids).await?;
+  encode(&server_params)
+}
+
+/// Builds the `RoutePlanServerParameter` for a validated
+/// `RoutePlanClientParameter`, resolving every POI id to its `PoiInfo`.
+/// Both the protobuf and the JSON requests go through here so they share
+/// the same semantics.
+///
+/// Start and end are taken either from their POI id or from their raw
+/// coordinates. Middle POIs are routed through the entry point of their
+/// `PoiInfo`. Yaw re-planning forwards `isYaw` with `routeIDInNavi`
+async fn client_to_server_params(
+  mut client_params: RoutePlanClientParameter,
+  services: &service::Service,
+  upstream_trace_ids: &UpstreamTraceIds,
+) -> Result<RoutePlanServerParameter, ApiError> {
   let mut server_params = RoutePlanServerParameter::new();
-  server_params.start = Option::Some(RoutePlanServerParameter_oneof_start::startPoi(
-    start_point_info.expect("start_point_info"),
-  ));
-  server_params.end = Option::Some(RoutePlanServerParameter_oneof_end::endPoi(
-    end_point_info.expect("end_point_info"),
-  ));
+  if !client_params.startPoiID.is_empty() {
+    let start_point_info = services
+      .get_poi_info(
+        &client_params.startPoiID,
+        client_params.mode,
+        upstream_trace_ids,
+      )
+      .await?;
+    server_params.start = Option::Some(RoutePlanServerParameter_oneof_start::startPoi(
+      start_point_info,
+    ));
+  } else {
+    server_params.start = Option::Some(RoutePlanServerParameter_oneof_start::startPoint(
+      client_params.take_startPoint(),
+    ));
+  }
+  if !client_params.endPoiID.is_empty() {
+    let end_point_info = services
+      .get_poi_info(
+        &client_params.endPoiID,
+        client_params.mode,
+        upstream_trace_ids,
+      )
+      .await?;
+    server_params.end = Option::Some(RoutePlanServerParameter_oneof_end::endPoi(end_point_info));
+  } else {
+    server_params.end = Option::Some(RoutePlanServerParameter_oneof_end::endPoint(
+      client_params.take_endPoint(),
+    ));
+  }
+  if client_params.middlePoiID.is_empty() {
+    server_params.middlePoint = client_params.take_middlePoint();
+  } else {
+    for middle_poi_id in client_params.middlePoiID.iter() {
+      let mut middle_point_info = services
+        .get_poi_info(middle_poi_id, client_params.mode, upstream_trace_ids)
+        .await?;
+      server_params
+        .middlePoint
+        .push(middle_point_info.take_entry());
+    }
+    server_params.middlePoiID = client_params.take_middlePoiID();
+  }
+
   server_params.version = client_params.version;
   server_params.mode = client_params.mode;
   server_params.policy = client_params.policy;
   server_params.realTimeTraffic = client_params.realTimeTraffic;
-  let server_params_bytes = server_params.write_to_bytes().expect("server_params_bytes");
-  // let ret = String::from_utf8(server_params_bytes).expect("server_params_bytes");
-  return Ok(server_params_bytes);
+  server_params.responseMode = client_params.responseMode;
+  server_params.shapeOptimization = client_params.shapeOptimization;
+  server_params.routeIDInNavi = client_params.take_routeIDInNavi();
+  server_params.roadFormForStartPoint = client_params.roadFormForStartPoint;
+  server_params.isYaw = client_params.isYaw;
+  server_params.avoidAreas = client_params.take_avoidAreas();
+  server_params.avoidRoadIDs = client_params.take_avoidRoadIDs();
+  Ok(server_params)
 }
 
-pub async fn client_json_to_server_protobuf(
-  data: &String,
-  services: &service::Service,
-) -> Result<Vec<u8>, Box<dyn Error>> {
-  let json_object =
-    serde_json::from_str::<serde_json::value::Value>(data.as_str()).expect("json_object");
-  let version = json_object["version"].as_u64().expect("version") as u32;
-  let mode = if let Option::Some(opt) = json_object["mode"].as_u64() {
-    opt as u32
-  } else {
-    0
-  };
-  let policy = if let Option::Some(opt) = json_object["policy"].as_u64() {
-    opt as u32
-  } else {
-    0
+fn encode(server_params: &RoutePlanServerParameter) -> Result<Vec<u8>, ApiError> {
+  server_params
+    .write_to_bytes()
+    .map_err(|error| ApiError::BadInput(error.to_string()))
+}
+
+/// Reads a `RoutePlanClientParameter` from a JSON request following the
+/// protobuf canonical JSON mapping, collecting a problem for every field
+/// with a mistyped value.
+///
+/// As in the canonical mapping absent and `null` fields take their default
+/// value and integers and doubles are also accepted as strings.
+/// `modelId` is still accepted for the `modelID` field of a `GeoPoint`,
+/// any other unknown field is rejected
+fn client_params_from_json(data: &str) -> Result<RoutePlanClientParameter, ApiError> {
+  let mut errors = ValidationErrors::new();
+  let json_object = match serde_json::from_str::<Value>(data) {
+    Ok(json_object) if json_object.is_object() => json_object,
+    Ok(_) => {
+      errors.add("body", "must be a JSON object");
+      return Err(errors.into());
+    }
+    Err(error) => {
+      errors.add("body", &error.to_string());
+      return Err(errors.into());
+    }
   };
-  let realTimeTraffic = if let Option::Some(opt) = json_object["realTimeTraffic"].as_bool() {
-    opt
-  } else {
-    false
+  if let Some(path) = unknown_client_field(&json_object) {
+    return Err(ApiError::BadInput(format!("unknown field {}", path)));
+  }
+
+  let mut client_params = RoutePlanClientParameter::new();
+  client_params.version = json_u32(&json_object["version"], "version", &mut errors);
+  client_params.mode = json_u32(&json_object["mode"], "mode", &mut errors);
+  client_params.policy = json_u32(&json_object["policy"], "policy", &mut errors);
+  client_params.realTimeTraffic = json_bool(
+    &json_object["realTimeTraffic"],
+    "realTimeTraffic",
+    &mut errors,
+  );
+  client_params.startPoint = SingularPtrField::from_option(json_point(
+    &json_object["startPoint"],
+    "startPoint",
+    &mut errors,
+  ));
+  client_params.endPoint = SingularPtrField::from_option(json_point(
+    &json_object["endPoint"],
+    "endPoint",
+    &mut errors,
+  ));
+  client_params.middlePoint = json_repeated(
+    &json_object["middlePoint"],
+    "middlePoint",
+    &mut errors,
+    |value, path, errors| {
+      if value.is_null() {
+        errors.add(path, "must be an object");
+      }
+      json_point(value, path, errors).unwrap_or_default()
+    },
+  );
+  client_params.startPoiID = json_string(&json_object["startPoiID"], "startPoiID", &mut errors);
+  client_params.endPoiID = json_string(&json_object["endPoiID"], "endPoiID", &mut errors);
+  client_params.middlePoiID = json_repeated(
+    &json_object["middlePoiID"],
+    "middlePoiID",
+    &mut errors,
+    json_string,
+  );
+  client_params.responseMode = json_u32(&json_object["responseMode"], "responseMode", &mut errors);
+  client_params.shapeOptimization = json_u32(
+    &json_object["shapeOptimization"],
+    "shapeOptimization",
+    &mut errors,
+  );
+  client_params.routeIDInNavi =
+    json_string(&json_object["routeIDInNavi"], "routeIDInNavi", &mut errors);
+  client_params.roadFormForStartPoint = json_u32(
+    &json_object["roadFormForStartPoint"],
+    "roadFormForStartPoint",
+    &mut errors,
+  );
+  client_params.isYaw = json_u32(&json_object["isYaw"], "isYaw", &mut errors);
+  client_params.avoidAreas = json_repeated(
+    &json_object["avoidAreas"],
+    "avoidAreas",
+    &mut errors,
+    json_area,
+  );
+  client_params.avoidRoadIDs = json_repeated(
+    &json_object["avoidRoadIDs"],
+    "avoidRoadIDs",
+    &mut errors,
+    json_string,
+  );
+
+  errors.into_result()?;
+  Ok(client_params)
+}
+
+/// Path of the first key of a JSON `RoutePlanClientParameter` which is not
+/// one of its fields, or of its `GeoPoint` and `GeoArea` fields
+fn unknown_client_field(json_object: &Value) -> Option<String> {
+  let point = GeoPoint::descriptor_static();
+  let area = GeoArea::descriptor_static();
+  let items = |key: &'static str| {
+    json_object[key]
+      .as_array()
+      .into_iter()
+      .flatten()
+      .enumerate()
+      .map(move |(index, value)| (value, format!("{}[{}]", key, index)))
   };
-  let startPoiID = if let Option::Some(opt) = json_object["startPoiID"].as_str() {
-    opt
+  let points = ["startPoint", "endPoint"]
+    .iter()
+    .map(|key| (&json_object[*key], String::from(*key)))
+    .chain(items("middlePoint"));
+
+  unknown_field(
+    json_object,
+    RoutePlanClientParameter::descriptor_static(),
+    "",
+    &[],
+  )
+  .or_else(|| {
+    points
+      .filter_map(|(value, path)| unknown_field(value, point, &path, &["modelId"]))
+      .next()
+  })
+  .or_else(|| {
+    items("avoidAreas")
+      .filter_map(|(value, path)| unknown_field(value, area, &path, &[]))
+      .next()
+  })
+}
+
+/// Path of the first key of a JSON object which is neither a field of
+/// `descriptor`, by its name or its JSON name, nor one of `legacy`
+fn unknown_field(
+  value: &Value,
+  descriptor: &MessageDescriptor,
+  path: &str,
+  legacy: &[&str],
+) -> Option<String> {
+  let object = value.as_object()?;
+  object
+    .keys()
+    .find(|key| {
+      !legacy.contains(&key.as_str())
+        && !descriptor
+          .fields()
+          .iter()
+          .any(|field| field.name() == key.as_str() || field.json_name() == key.as_str())
+    })
+    .map(|key| match path {
+      "" => key.clone(),
+      path => format!("{}.{}", path, key),
+    })
+}
+
+fn json_point(value: &Value, path: &str, errors: &mut ValidationErrors) -> Option<GeoPoint> {
+  if value.is_null() {
+    return None;
+  }
+  if !value.is_object() {
+    errors.add(path, "must be an object");
+    return None;
+  }
+
+  let model_id = if value["modelID"].is_null() {
+    &value["modelId"]
   } else {
-    ""
+    &value["modelID"]
   };
-  let endPoiID = if let Option::Some(opt) = json_object["endPoiID"].as_str() {
-    opt
-  } else {
-    ""
+  let mut point = GeoPoint::new();
+  point.longitude = json_f64(&value["longitude"], &format!("{}.longitude", path), errors);
+  point.latitude = json_f64(&value["latitude"], &format!("{}.latitude", path), errors);
+  point.height = json_i32(&value["height"], &format!("{}.height", path), errors);
+  point.modelID = json_u32(model_id, &format!("{}.modelID", path), errors);
+  point.floor = json_i32(&value["floor"], &format!("{}.floor", path), errors);
+  Some(point)
+}
+
+fn json_area(value: &Value, path: &str, errors: &mut ValidationErrors) -> GeoArea {
+  let mut area = GeoArea::new();
+  if !value.is_object() {
+    errors.add(path, "must be an object");
+    return area;
+  }
+
+  area.minx = json_f64(&value["minx"], &format!("{}.minx", path), errors);
+  area.miny = json_f64(&value["miny"], &format!("{}.miny", path), errors);
+  area.maxx = json_f64(&value["maxx"], &format!("{}.maxx", path), errors);
+  area.maxy = json_f64(&value["maxy"], &format!("{}.maxy", path), errors);
+  area
+}
+
+fn json_repeated<T, F>(
+  value: &Value,
+  path: &str,
+  errors: &mut ValidationErrors,
+  item: F,
+) -> RepeatedField<T>
+where
+  F: Fn(&Value, &str, &mut ValidationErrors) -> T,
+{
+  if value.is_null() {
+    return RepeatedField::new();
+  }
+  match value.as_array() {
+    Some(values) => RepeatedField::from_vec(
+      values
+        .iter()
+        .enumerate()
+        .map(|(index, value)| item(value, &format!("{}[{}]", path, index), errors))
+        .collect(),
+    ),
+    None => {
+      errors.add(path, "must be an array");
+      RepeatedField::new()
+    }
+  }
+}
+
+fn json_u32(value: &Value, path: &str, errors: &mut ValidationErrors) -> u32 {
+  if value.is_null() {
+    return 0;
+  }
+  let opt = match value {
+    Value::String(opt) => opt.parse::<u32>().ok(),
+    _ => value
+      .as_u64()
+      .filter(|opt| *opt <= u32::MAX as u64)
+      .map(|opt| opt as u32),
   };
+  match opt {
+    Some(opt) => opt,
+    None => {
+      errors.add(path, "must be an unsigned 32-bit integer");
+      0
+    }
+  }
+}
 
-  let mut server_params = RoutePlanServerParameter::new();
-  if "" != startPoiID {
-    let start_point_info = services.get_poi_info(&String::from(startPoiID)).await;
-    server_params.start = Option::Some(RoutePlanServerParameter_oneof_start::startPoi(
-      start_point_info.expect("start_point_info"),
-    ));
-  } else {
-    let mut startPoint = GeoPoint::new();
-    startPoint.longitude = json_object["startPoint"]["longitude"]
-      .as_f64()
-      .expect("longitude");
-    startPoint.latitude = json_object["startPoint"]["latitude"]
-      .as_f64()
-      .expect("latitude");
-    startPoint.height = json_object["startPoint"]["height"]
+fn json_i32(value: &Value, path: &str, errors: &mut ValidationErrors) -> i32 {
+  if value.is_null() {
+    return 0;
+  }
+  let opt = match value {
+    Value::String(opt) => opt.parse::<i32>().ok(),
+    _ => value
       .as_i64()
-      .expect("height") as i32;
-    startPoint.floor = if let Option::Some(opt) = json_object["startPoint"]["floor"].as_i64() {
-      opt as i32
-    } else {
-      0
-    };
-    startPoint.modelID = if let Option::Some(opt) = json_object["startPoint"]["modelId"].as_u64() {
-      opt as u32
-    } else {
+      .filter(|opt| *opt >= i32::MIN as i64 && *opt <= i32::MAX as i64)
+      .map(|opt| opt as i32),
+  };
+  match opt {
+    Some(opt) => opt,
+    None => {
+      errors.add(path, "must be a 32-bit integer");
       0
-    };
-    server_params.start =
-      Option::Some(RoutePlanServerParameter_oneof_start::startPoint(startPoint));
+    }
   }
-  if "" != endPoiID {
-    let end_point_info = services.get_poi_info(&String::from(endPoiID)).await;
-    server_params.end = Option::Some(RoutePlanServerParameter_oneof_end::endPoi(
-      end_point_info.expect("end_point_info"),
-    ));
-  } else {
-    let mut endPoint = GeoPoint::new();
-    endPoint.longitude = json_object["endPoint"]["longitude"]
-      .as_f64()
-      .expect("longitude");
-    endPoint.latitude = json_object["endPoint"]["latitude"]
-      .as_f64()
-      .expect("latitude");
-    endPoint.height = json_object["endPoint"]["height"].as_i64().expect("height") as i32;
-    endPoint.floor = if let Option::Some(opt) = json_object["endPoint"]["floor"].as_i64() {
-      opt as i32
-    } else {
-      0
-    };
-    endPoint.modelID = if let Option::Some(opt) = json_object["endPoint"]["modelId"].as_u64() {
-      opt as u32
-    } else {
-      0
+}
+
+fn json_f64(value: &Value, path: &str, errors: &mut ValidationErrors) -> f64 {
+  if value.is_null() {
+    return 0f64;
+  }
+  let opt = match value {
+    Value::String(opt) => opt.parse::<f64>().ok(),
+    _ => value.as_f64(),
+  };
+  match opt {
+    Some(opt) => opt,
+    None => {
+      errors.add(path, "must be a number");
+      0f64
+    }
+  }
+}
+
+fn json_bool(value: &Value, path: &str, errors: &mut ValidationErrors) -> bool {
+  if value.is_null() {
+    return false;
+  }
+  match value.as_bool() {
+    Some(opt) => opt,
+    None => {
+      errors.add(path, "must be a boolean");
+      false
+    }
+  }
+}
+
+fn json_string(value: &Value, path: &str, errors: &mut ValidationErrors) -> String {
+  if value.is_null() {
+    return String::new();
+  }
+  match value.as_str() {
+    Some(opt) => String::from(opt),
+    None => {
+      errors.add(path, "must be a string");
+      String::new()
+    }
+  }
+}
+
+#[cfg(test)]
+pub mod tests {
+  use super::*;
+  use crate::addon::api_server::proto_json;
+  use crate::config::Config;
+  use hyper::service::{make_service_fn, service_fn};
+  use hyper::{Body, Response, Server};
+  use std::convert::Infallible;
+  use std::sync::Arc;
+
+  const FULL_REQUEST: &str = r#"{
+    "version": 1,
+    "mode": 1,
+    "policy": 2,
+    "realTimeTraffic": true,
+    "startPoint": { "longitude": 116.4418912826, "latitude": 39.9090135175, "modelID": 3, "floor": -1 },
+    "middlePoint": [
+      { "longitude": 116.44, "latitude": "39.91" },
+      { "longitude": 116.43, "latitude": 39.912, "height": 2 }
+    ],
+    "endPoiID": "21",
+    "responseMode": 1,
+    "shapeOptimization": "1",
+    "routeIDInNavi": "route-1",
+    "roadFormForStartPoint": 4,
+    "isYaw": 1,
+    "avoidAreas": [{ "minx": 116.40, "miny": 39.90, "maxx": 116.41, "maxy": 39.91 }],
+    "avoidRoadIDs": ["1001", "1002"]
+  }"#;
+
+  #[test]
+  fn reads_json_client_params() {
+    let client_params = client_params_from_json(FULL_REQUEST).unwrap();
+
+    assert_eq!(client_params.version, 1);
+    assert_eq!(client_params.policy, 2);
+    assert_eq!(client_params.get_startPoint().longitude, 116.4418912826);
+    assert_eq!(client_params.get_startPoint().floor, -1);
+    assert_eq!(client_params.middlePoint.len(), 2);
+    assert_eq!(client_params.middlePoint[0].latitude, 39.91);
+    assert_eq!(client_params.endPoiID, "21");
+    assert!(!client_params.has_endPoint());
+    assert_eq!(client_params.shapeOptimization, 1);
+    assert_eq!(client_params.routeIDInNavi, "route-1");
+    assert_eq!(client_params.isYaw, 1);
+    assert_eq!(client_params.avoidAreas[0].maxy, 39.91);
+    assert_eq!(client_params.avoidRoadIDs.to_vec(), vec!["1001", "1002"]);
+  }
+
+  #[test]
+  fn follows_protobuf_canonical_json_mapping() {
+    let client_params = client_params_from_json(FULL_REQUEST).unwrap();
+    let canonical = proto_json::to_json(&client_params).to_string();
+
+    assert_eq!(client_params_from_json(&canonical).unwrap(), client_params);
+  }
+
+  #[test]
+  fn accepts_legacy_model_id() {
+    let client_params = client_params_from_json(
+      r#"{ "startPoint": { "longitude": 116.44, "latitude": 39.90, "modelId": 7 } }"#,
+    )
+    .unwrap();
+
+    assert_eq!(client_params.get_startPoint().modelID, 7);
+  }
+
+  #[test]
+  fn collects_json_field_errors() {
+    let errors = client_params_from_json(
+      r#"{
+        "mode": -1,
+        "startPoint": { "longitude": "east", "height": 0 },
+        "endPoiID": 21,
+        "middlePoiID": "22",
+        "avoidAreas": [{ "minx": true }]
+      }"#,
+    );
+
+    let errors = match errors {
+      Err(ApiError::Validation(errors)) => errors,
+      ret => panic!("unexpected {:?}", ret),
     };
-    server_params.end = Option::Some(RoutePlanServerParameter_oneof_end::endPoint(endPoint));
+    assert_eq!(
+      errors.to_string(),
+      "mode: must be an unsigned 32-bit integer; \
+       startPoint.longitude: must be a number; \
+       endPoiID: must be a string; \
+       middlePoiID: must be an array; \
+       avoidAreas[0].minx: must be a number"
+    );
   }
 
-  server_params.version = version;
-  server_params.mode = mode;
-  server_params.policy = policy;
-  server_params.realTimeTraffic = realTimeTraffic;
-  let server_params_bytes = server_params.write_to_bytes().expect("server_params_bytes");
-  // let ret = String::from_utf8(server_params_bytes).expect("server_params_bytes");
-  return Ok(server_params_bytes);
+  #[tokio::test]
+  async fn forwards_binary_client_params() {
+    let mut start_point = GeoPoint::new();
+    start_point.longitude = 116.447209;
+    start_point.latitude = 39.912554;
+    let mut end_point = GeoPoint::new();
+    end_point.longitude = 116.452512;
+    end_point.latitude = 39.909454;
+    let mut client_params = RoutePlanClientParameter::new();
+    client_params.version = 1;
+    client_params.startPoint = SingularPtrField::some(start_point.clone());
+    client_params.endPoint = SingularPtrField::some(end_point.clone());
+    client_params.middlePoint.push(end_point.clone());
+    client_params.isYaw = 1;
+    client_params.routeIDInNavi = String::from("route-1");
+    client_params.avoidRoadIDs.push(String::from("1001"));
+    let data = client_params.write_to_bytes().unwrap();
+
+    let upstream_trace_ids = UpstreamTraceIds::default();
+    let encoded =
+      client_to_server_protobuf(&data, &service::Service::default(), &upstream_trace_ids)
+        .await
+        .unwrap();
+    let server_params = RoutePlanServerParameter::parse_from_bytes(&encoded).unwrap();
+
+    assert_eq!(server_params.version, 1);
+    assert_eq!(server_params.get_startPoint(), &start_point);
+    assert_eq!(server_params.get_endPoint(), &end_point);
+    assert_eq!(server_params.middlePoint.len(), 1);
+    assert_eq!(server_params.isYaw, 1);
+    assert_eq!(server_params.routeIDInNavi, "route-1");
+    assert_eq!(server_params.avoidRoadIDs.to_vec(), vec!["1001"]);
+    // no POI was looked up
+    assert_eq!(upstream_trace_ids.header_value(), None);
+  }
+
+  #[tokio::test]
+  async fn forwards_poi_trace_ids() {
+    let make_service = make_service_fn(|_| async {
+      Ok::<_, Infallible>(service_fn(|_| async {
+        let body = r#"{"rtnCode":"000000","traceId":"poi-trace-1","body":{"data":
+          {"poiId":"21","poiName":"entrance","longitude":"116.452512","latitude":"39.909454"}}}"#;
+        Ok::<_, Infallible>(Response::new(Body::from(body)))
+      }))
+    });
+    let poi_server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+    let mut config = Config::default();
+    config.poi_server = format!("http://{}/", poi_server.local_addr());
+    config.route_cache_config.backend = String::from("memory");
+    config.engine_config.kind = String::from("mock");
+    tokio::spawn(poi_server);
+    let services = service::Service::new(Arc::new(config)).unwrap();
+
+    let mut start_point = GeoPoint::new();
+    start_point.longitude = 116.447209;
+    start_point.latitude = 39.912554;
+    let mut client_params = RoutePlanClientParameter::new();
+    client_params.startPoint = SingularPtrField::some(start_point);
+    client_params.endPoiID = String::from("21");
+    let data = client_params.write_to_bytes().unwrap();
+
+    let upstream_trace_ids = UpstreamTraceIds::default();
+    let encoded = client_to_server_protobuf(&data, &services, &upstream_trace_ids)
+      .await
+      .unwrap();
+    let server_params = RoutePlanServerParameter::parse_from_bytes(&encoded).unwrap();
+
+    assert_eq!(server_params.get_endPoi().poiID, 21);
+    assert_eq!(
+      upstream_trace_ids.header_value(),
+      Some(String::from("poi-trace-1"))
+    );
+  }
+
+  #[test]
+  fn rejects_malformed_json() {
+    match client_params_from_json("[1, 2]") {
+      Err(ApiError::Validation(errors)) => {
+        assert_eq!(errors.to_string(), "body: must be a JSON object")
+      }
+      ret => panic!("unexpected {:?}", ret),
+    }
+  }
+
+  #[test]
+  fn rejects_unknown_fields() {
+    let unknown = |data: &str| match client_params_from_json(data) {
+      Err(ApiError::BadInput(reason)) => reason,
+      ret => panic!("unexpected {:?}", ret),
+    };
+
+    assert_eq!(unknown(r#"{ "polcy": 2 }"#), "unknown field polcy");
+    assert_eq!(
+      unknown(r#"{ "startPoint": { "longitude": 116.44, "lat": 39.90 } }"#),
+      "unknown field startPoint.lat"
+    );
+    assert_eq!(
+      unknown(r#"{ "middlePoint": [{}, { "floors": 1 }] }"#),
+      "unknown field middlePoint[1].floors"
+    );
+    assert_eq!(
+      unknown(r#"{ "avoidAreas": [{ "minx": 1, "minz": 2 }] }"#),
+      "unknown field avoidAreas[0].minz"
+    );
+  }
 }
@@ -0,0 +1,579 @@
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use crate::protos::route_common::GeoArea;
+
+/// Mean radius of the earth in meters
+const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// Precision of the coordinates joining roads, 1e-7 degrees is about 1 cm
+const NODE_PRECISION: f64 = 1e7;
+
+/// Location as `(longitude, latitude)` in degrees
+pub type Location = (f64, f64);
+
+/// Distance in meters between two locations
+pub fn distance(from: Location, to: Location) -> f64 {
+  let (from_lat, to_lat) = (from.1.to_radians(), to.1.to_radians());
+  let d_lat = to_lat - from_lat;
+  let d_lon = (to.0 - from.0).to_radians();
+  let a = (d_lat / 2.0).sin().powi(2) + from_lat.cos() * to_lat.cos() * (d_lon / 2.0).sin().powi(2);
+  2.0 * EARTH_RADIUS * a.sqrt().asin()
+}
+
+/// Initial bearing from `from` to `to` in degrees clockwise from north
+pub fn bearing(from: Location, to: Location) -> f64 {
+  let (from_lat, to_lat) = (from.1.to_radians(), to.1.to_radians());
+  let d_lon = (to.0 - from.0).to_radians();
+  let y = d_lon.sin() * to_lat.cos();
+  let x = from_lat.cos() * to_lat.sin() - from_lat.sin() * to_lat.cos() * d_lon.cos();
+  (y.atan2(x).to_degrees() + 360.0) % 360.0
+}
+
+/// Whether the segment from `a` to `b` has a point in `area`
+fn segment_crosses(a: Location, b: Location, area: &GeoArea) -> bool {
+  // Liang-Barsky clipping of the segment by the area
+  let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+  let mut enter = 0.0_f64;
+  let mut exit = 1.0_f64;
+  for (p, q) in [
+    (-dx, a.0 - area.minx),
+    (dx, area.maxx - a.0),
+    (-dy, a.1 - area.miny),
+    (dy, area.maxy - a.1),
+  ]
+  .iter()
+  {
+    if 0.0 == *p {
+      if *q < 0.0 {
+        return false;
+      }
+    } else if *p < 0.0 {
+      enter = enter.max(q / p);
+    } else {
+      exit = exit.min(q / p);
+    }
+  }
+  enter <= exit
+}
+
+/// A walkable road between two nodes, walked both ways
+#[derive(Debug, Clone, PartialEq)]
+pub struct Road {
+  pub id: u64,
+  pub name: String,
+  /// `RoadForm.form` of the road, the facility announced by the guidance
+  pub form: u32,
+  pub class: u32,
+  /// Indoor model of the road, 0 outdoors
+  pub model_id: u32,
+  /// Floor of the `from` node
+  pub floor: i32,
+  /// Floor of the `to` node, another floor than `floor` on stairs,
+  /// escalators and elevators
+  pub to_floor: i32,
+  pub from: usize,
+  pub to: usize,
+  /// Locations from `from` to `to`
+  pub geometry: Vec<Location>,
+  /// Length in meters
+  pub length: f64,
+}
+
+impl Road {
+  fn new(id: u64, name: String, form: u32, class: u32, geometry: Vec<Location>) -> Road {
+    let length = geometry
+      .windows(2)
+      .map(|segment| distance(segment[0], segment[1]))
+      .sum();
+    Road {
+      id,
+      name,
+      form,
+      class,
+      model_id: 0,
+      floor: 0,
+      to_floor: 0,
+      from: 0,
+      to: 0,
+      geometry,
+      length,
+    }
+  }
+
+  /// Whether the road goes from a floor to another
+  pub fn is_vertical(&self) -> bool {
+    self.floor != self.to_floor
+  }
+
+  /// Floor of the road at `node`
+  pub fn floor_at(&self, node: usize) -> i32 {
+    if node == self.to {
+      self.to_floor
+    } else {
+      self.floor
+    }
+  }
+
+  /// Bearing of the road leaving `node`
+  pub fn bearing_from(&self, node: usize) -> f64 {
+    let last = self.geometry.len() - 1;
+    if node == self.from {
+      bearing(self.geometry[0], self.geometry[1])
+    } else {
+      bearing(self.geometry[last], self.geometry[last - 1])
+    }
+  }
+
+  /// Bearing of the road arriving at `node`
+  pub fn bearing_into(&self, node: usize) -> f64 {
+    let last = self.geometry.len() - 1;
+    if node == self.to {
+      bearing(self.geometry[last - 1], self.geometry[last])
+    } else {
+      bearing(self.geometry[1], self.geometry[0])
+    }
+  }
+
+  /// Whether a part of the road is in `area`
+  pub fn crosses(&self, area: &GeoArea) -> bool {
+    self
+      .geometry
+      .windows(2)
+      .any(|segment| segment_crosses(segment[0], segment[1], area))
+  }
+
+  /// Locations of the road from `from` to `to` meters along it, walked
+  /// backwards when `to` is before `from`
+  pub fn slice(&self, from: f64, to: f64) -> Vec<Location> {
+    if to < from {
+      let mut slice = self.slice(to, from);
+      slice.reverse();
+      return slice;
+    }
+    let (from, to) = (from.max(0.0), to.min(self.length));
+    let mut slice = Vec::new();
+    let mut start = 0.0;
+    for segment in self.geometry.windows(2) {
+      let length = distance(segment[0], segment[1]);
+      let end = start + length;
+      let at = |along: f64| {
+        let ratio = if 0.0 == length {
+          0.0
+        } else {
+          (along - start) / length
+        };
+        if ratio <= 0.0 {
+          segment[0]
+        } else if 1.0 <= ratio {
+          segment[1]
+        } else {
+          (
+            segment[0].0 + (segment[1].0 - segment[0].0) * ratio,
+            segment[0].1 + (segment[1].1 - segment[0].1) * ratio,
+          )
+        }
+      };
+      if slice.is_empty() && from <= end {
+        slice.push(at(from));
+      }
+      if !slice.is_empty() {
+        if to <= end {
+          slice.push(at(to));
+          break;
+        }
+        slice.push(segment[1]);
+      }
+      start = end;
+    }
+    slice
+  }
+}
+
+/// The point of a road closest to a location
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Projection {
+  pub road: usize,
+  pub location: Location,
+  /// Meters along the road from its `from` node
+  pub along: f64,
+  /// Meters between the projected location and the road
+  pub distance: f64,
+}
+
+/// Walkable network of roads joined at their ends, loaded from a GeoJSON
+/// `FeatureCollection` of `LineString`s or from a CSV file of WKT
+/// `LINESTRING`s.
+///
+/// Every line is a road, with the `id`, `name`, `form`, `class`,
+/// `modelID`, `floor` and `toFloor` properties or columns. Roads sharing an
+/// end location on the same floor are joined, a road with a `toFloor`
+/// ends on that floor and joins the roads of both floors
+#[derive(Debug, Default)]
+pub struct RoadGraph {
+  pub nodes: Vec<Location>,
+  pub roads: Vec<Road>,
+  adjacency: Vec<Vec<usize>>,
+  index: HashMap<(i64, i64, i32), usize>,
+  /// `(modelID, floor)` of every floor with roads
+  floors: HashSet<(u32, i32)>,
+}
+
+impl RoadGraph {
+  /// Loads a `.csv` file as CSV and any other file as GeoJSON
+  pub fn load(path: &str) -> Result<RoadGraph, String> {
+    let content = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+    let is_csv = Path::new(path)
+      .extension()
+      .and_then(|extension| extension.to_str())
+      .map_or(false, |extension| extension.eq_ignore_ascii_case("csv"));
+    if is_csv {
+      RoadGraph::from_csv(&content)
+    } else {
+      RoadGraph::from_geojson(&content)
+    }
+  }
+
+  pub fn from_geojson(json: &str) -> Result<RoadGraph, String> {
+    let json = serde_json::from_str::<Value>(json).map_err(|error| error.to_string())?;
+    let features = json["features"]
+      .as_array()
+      .ok_or_else(|| String::from("no features"))?;
+    let mut graph = RoadGraph::default();
+    for (index, feature) in features.iter().enumerate() {
+      let properties = &feature["properties"];
+      let field = |name: &str| -> Result<u64, String> {
+        match &properties[name] {
+          Value::Null => Some(0),
+          Value::Number(value) => value.as_u64(),
+          Value::String(value) => value.trim().parse::<u64>().ok(),
+          _ => None,
+        }
+        .ok_or_else(|| format!("feature {}: invalid {}", index, name))
+      };
+      let floor_field = |name: &str| -> Result<Option<i32>, String> {
+        match &properties[name] {
+          Value::Null => Some(None),
+          Value::Number(value) => value.as_i64().map(|value| Some(value as i32)),
+          Value::String(value) => value.trim().parse::<i32>().ok().map(Some),
+          _ => None,
+        }
+        .ok_or_else(|| format!("feature {}: invalid {}", index, name))
+      };
+      let (id, form, class) = (field("id")?, field("form")?, field("class")?);
+      let model_id = field("modelID")? as u32;
+      let floor = floor_field("floor")?.unwrap_or_default();
+      let to_floor = floor_field("toFloor")?.unwrap_or(floor);
+      let name = String::from(properties["name"].as_str().unwrap_or_default());
+      let lines = match feature["geometry"]["type"].as_str() {
+        Some("LineString") => vec![&feature["geometry"]["coordinates"]],
+        Some("MultiLineString") => feature["geometry"]["coordinates"]
+          .as_array()
+          .map(|lines| lines.iter().collect())
+          .unwrap_or_default(),
+        // points and areas are not walkable
+        _ => continue,
+      };
+      for line in lines {
+        let geometry = line
+          .as_array()
+          .and_then(|positions| {
+            positions
+              .iter()
+              .map(|position| Some((position[0].as_f64()?, position[1].as_f64()?)))
+              .collect::<Option<Vec<Location>>>()
+          })
+          .ok_or_else(|| format!("feature {}: invalid coordinates", index))?;
+        graph.add_road(Road {
+          model_id,
+          floor,
+          to_floor,
+          ..Road::new(id, name.clone(), form as u32, class as u32, geometry)
+        });
+      }
+    }
+    Ok(graph)
+  }
+
+  /// Reads the `id`, `name`, `form`, `class`, `modelID`, `floor`,
+  /// `toFloor` and `geometry` columns named by the header, in any order
+  pub fn from_csv(csv: &str) -> Result<RoadGraph, String> {
+    // numbered before the blank lines are skipped, as in the file
+    let mut lines = csv
+      .lines()
+      .enumerate()
+      .map(|(index, line)| (index + 1, line))
+      .filter(|(_, line)| !line.trim().is_empty());
+    let (_, header) = lines.next().ok_or_else(|| String::from("no header"))?;
+    let header = split_csv_line(header);
+    let column = |name: &str| {
+      header
+        .iter()
+        .position(|column| column.trim().eq_ignore_ascii_case(name))
+    };
+    let geometry_column = column("geometry").ok_or_else(|| String::from("no geometry column"))?;
+    let (id_column, name_column) = (column("id"), column("name"));
+    let (form_column, class_column) = (column("form"), column("class"));
+    let model_column = column("modelID");
+    let (floor_column, to_floor_column) = (column("floor"), column("toFloor"));
+
+    let mut graph = RoadGraph::default();
+    for (line_number, line) in lines {
+      let fields = split_csv_line(line);
+      let text = |column: Option<usize>| {
+        column
+          .and_then(|column| fields.get(column))
+          .map(|field| field.trim())
+          .unwrap_or_default()
+      };
+      let number = |name: &str, column: Option<usize>| match text(column) {
+        "" => Ok(0),
+        value => value
+          .parse::<u64>()
+          .map_err(|_| format!("line {}: invalid {}", line_number, name)),
+      };
+      let floor = |name: &str, column: Option<usize>| match text(column) {
+        "" => Ok(None),
+        value => value
+          .parse::<i32>()
+          .map(Some)
+          .map_err(|_| format!("line {}: invalid {}", line_number, name)),
+      };
+      let geometry = parse_wkt_line_string(text(Some(geometry_column)))
+        .ok_or_else(|| format!("line {}: invalid geometry", line_number))?;
+      let from_floor = floor("floor", floor_column)?.unwrap_or_default();
+      graph.add_road(Road {
+        model_id: number("modelID", model_column)? as u32,
+        floor: from_floor,
+        to_floor: floor("toFloor", to_floor_column)?.unwrap_or(from_floor),
+        ..Road::new(
+          number("id", id_column)?,
+          String::from(text(name_column)),
+          number("form", form_column)? as u32,
+          number("class", class_column)? as u32,
+          geometry,
+        )
+      });
+    }
+    Ok(graph)
+  }
+
+  fn node(&mut self, location: Location, floor: i32) -> usize {
+    let key = (
+      (location.0 * NODE_PRECISION).round() as i64,
+      (location.1 * NODE_PRECISION).round() as i64,
+      floor,
+    );
+    if let Some(node) = self.index.get(&key) {
+      return *node;
+    }
+    self.nodes.push(location);
+    self.adjacency.push(Vec::new());
+    self.index.insert(key, self.nodes.len() - 1);
+    self.nodes.len() - 1
+  }
+
+  /// Adds `road` between the nodes at its ends, roads without length are
+  /// dropped unless they go to another floor
+  fn add_road(&mut self, mut road: Road) {
+    road.geometry.dedup();
+    if road.is_vertical() && 1 == road.geometry.len() {
+      // an elevator goes up on the spot
+      road.geometry.push(road.geometry[0]);
+    }
+    if road.geometry.len() < 2 {
+      return;
+    }
+    road.from = self.node(road.geometry[0], road.floor);
+    road.to = self.node(road.geometry[road.geometry.len() - 1], road.to_floor);
+    self.floors.insert((road.model_id, road.floor));
+    self.floors.insert((road.model_id, road.to_floor));
+    self.adjacency[road.from].push(self.roads.len());
+    if road.to != road.from {
+      self.adjacency[road.to].push(self.roads.len());
+    }
+    self.roads.push(road);
+  }
+
+  /// Whether a road of the model `model_id` is on `floor`
+  pub fn has_floor(&self, model_id: u32, floor: i32) -> bool {
+    self.floors.contains(&(model_id, floor))
+  }
+
+  /// Roads ending at `node`
+  pub fn roads_at(&self, node: usize) -> &[usize] {
+    &self.adjacency[node]
+  }
+
+  /// The closest point to `location` on the roads accepted by `allowed`
+  pub fn project<F: Fn(usize) -> bool>(
+    &self,
+    location: Location,
+    allowed: F,
+  ) -> Option<Projection> {
+    // planar around `location`, precise enough for the nearby roads
+    let scale = location.1.to_radians().cos();
+    let mut closest: Option<Projection> = None;
+    for (index, road) in self.roads.iter().enumerate() {
+      if !allowed(index) {
+        continue;
+      }
+      let mut along = 0.0;
+      for segment in road.geometry.windows(2) {
+        let (a, b) = (segment[0], segment[1]);
+        let (dx, dy) = ((b.0 - a.0) * scale, b.1 - a.1);
+        let (px, py) = ((location.0 - a.0) * scale, location.1 - a.1);
+        let squared = dx * dx + dy * dy;
+        let ratio = if 0.0 == squared {
+          0.0
+        } else {
+          ((px * dx + py * dy) / squared).clamp(0.0, 1.0)
+        };
+        let point = (a.0 + (b.0 - a.0) * ratio, a.1 + (b.1 - a.1) * ratio);
+        let gap = distance(location, point);
+        if closest.map_or(true, |closest| gap < closest.distance) {
+          closest = Some(Projection {
+            road: index,
+            location: point,
+            along: along + distance(a, point),
+            distance: gap,
+          });
+        }
+        along += distance(a, b);
+      }
+    }
+    closest
+  }
+}
+
+/// Fields of a CSV line, fields in double quotes may hold commas and `""`
+/// for a quote
+fn split_csv_line(line: &str) -> Vec<String> {
+  let mut fields = vec![String::new()];
+  let mut quoted = false;
+  let mut chars = line.chars().peekable();
+  while let Some(c) = chars.next() {
+    match c {
+      '"' if quoted && Some(&'"') == chars.peek() => {
+        chars.next();
+        fields.last_mut().expect("field").push('"');
+      }
+      '"' => quoted = !quoted,
+      ',' if !quoted => fields.push(String::new()),
+      c => fields.last_mut().expect("field").push(c),
+    }
+  }
+  fields
+}
+
+/// Locations of a WKT `LINESTRING (x y, x y, ...)`
+fn parse_wkt_line_string(wkt: &str) -> Option<Vec<Location>> {
+  let wkt = wkt.trim();
+  if !wkt.to_ascii_uppercase().starts_with("LINESTRING") {
+    return None;
+  }
+  let coordinates = wkt["LINESTRING".len()..]
+    .trim()
+    .strip_prefix('(')?
+    .strip_suffix(')')?;
+  coordinates
+    .split(',')
+    .map(|position| {
+      let mut values = position
+        .split_whitespace()
+        .map(|value| value.parse::<f64>());
+      match (values.next(), values.next()) {
+        (Some(Ok(x)), Some(Ok(y))) => Some((x, y)),
+        _ => None,
+      }
+    })
+    .collect()
+}
+
+#[cfg(test)]
+pub mod tests {
+  use super::*;
+
+  #[test]
+  fn loads_csv_and_joins_roads() {
+    let csv = "id,name,form,class,geometry\n\
+      1,\"Gate, North\",0,1,\"LINESTRING (116.447 39.912, 116.448 39.912)\"\n\
+      2,Stairs,5,1,\"LINESTRING(116.448 39.912,116.448 39.913)\"\n";
+    let graph = RoadGraph::from_csv(csv).unwrap();
+
+    assert_eq!(graph.nodes.len(), 3);
+    assert_eq!(graph.roads[0].name, "Gate, North");
+    assert_eq!(graph.roads[1].form, 5);
+    assert_eq!(graph.roads[0].to, graph.roads[1].from);
+    assert_eq!(graph.roads_at(graph.roads[0].to), &[0, 1]);
+    assert_eq!(graph.roads[0].length.round(), 85.0);
+
+    let invalid = "id,geometry\n1,POINT (116.447 39.912)\n";
+    assert_eq!(
+      RoadGraph::from_csv(invalid).err(),
+      Some(String::from("line 2: invalid geometry"))
+    );
+    let invalid = "\nid,geometry\n\n1,\"LINESTRING (116.447 39.912, 116.448 39.912)\"\n\nx,\"LINESTRING (116.447 39.912, 116.448 39.912)\"\n";
+    assert_eq!(
+      RoadGraph::from_csv(invalid).err(),
+      Some(String::from("line 6: invalid id"))
+    );
+  }
+
+  #[test]
+  fn joins_floors_through_vertical_roads() {
+    let csv = "id,form,modelID,floor,toFloor,geometry\n\
+      1,0,12,1,,\"LINESTRING (116.44 39.91, 116.441 39.91)\"\n\
+      2,0,12,2,,\"LINESTRING (116.44 39.91, 116.441 39.91)\"\n\
+      3,8,12,1,2,\"LINESTRING (116.44 39.91, 116.44 39.91)\"\n";
+    let graph = RoadGraph::from_csv(csv).unwrap();
+
+    assert_eq!(graph.nodes.len(), 4);
+    assert_ne!(graph.roads[0].from, graph.roads[1].from);
+    assert!(graph.roads[2].is_vertical());
+    assert_eq!(graph.roads[2].length, 0.0);
+    assert_eq!(graph.roads[2].from, graph.roads[0].from);
+    assert_eq!(graph.roads[2].to, graph.roads[1].from);
+    assert_eq!(graph.roads[2].floor_at(graph.roads[1].from), 2);
+    assert!(graph.has_floor(12, 2));
+    assert!(!graph.has_floor(12, 3));
+    assert!(!graph.has_floor(0, 1));
+  }
+
+  #[test]
+  fn slices_and_projects_roads() {
+    let road = Road::new(
+      7,
+      String::new(),
+      0,
+      0,
+      vec![(116.447, 39.912), (116.448, 39.912), (116.448, 39.913)],
+    );
+    let first = distance(road.geometry[0], road.geometry[1]);
+
+    let slice = road.slice(first / 2.0, first + 1.0);
+    assert_eq!(slice.len(), 3);
+    assert!((slice[0].0 - 116.4475).abs() < 1e-9);
+    assert_eq!(slice[1], (116.448, 39.912));
+    let back = road.slice(first + 1.0, first / 2.0);
+    assert_eq!(back[0], slice[2]);
+
+    let mut graph = RoadGraph::default();
+    graph.add_road(road);
+    let projection = graph.project((116.4475, 39.9119), |_| true).unwrap();
+    assert!((projection.location.0 - 116.4475).abs() < 1e-9);
+    assert!((projection.along - first / 2.0).abs() < 1e-6);
+    assert_eq!(graph.project((116.4475, 39.9119), |_| false), None);
+
+    let mut area = GeoArea::new();
+    area.minx = 116.4479;
+    area.maxx = 116.4481;
+    area.miny = 39.9125;
+    area.maxy = 39.9126;
+    assert!(graph.roads[0].crosses(&area));
+    area.minx = 116.449;
+    area.maxx = 116.450;
+    assert!(!graph.roads[0].crosses(&area));
+  }
+}
@@ -18,18 +18,45 @@ int findPath(const unsigned char *param, unsigned int paramLength, unsigned char
   std::cout << "[c++ bridge] findPath in: " << paramLength << std::endl;
   // string scondition = condition;
   char *sid = NULL;
+  *result = NULL;
+  *size = 0;
+  *id = NULL;
+  *id_size = 0;
   int ret = StartSearchForServer(param, paramLength, *result, *size, sid, *id_size, format);
 
-  *id = (char *)malloc(sizeof(char) * ((*id_size) + 1));
-  memset(*id, 0, (*id_size) + 1);
-  memcpy(*id, sid, *id_size);
-
-  free(sid);
-  std::cout << "[c++ bridge] findPath out: " << ret << ":" << *size << ":" << *id << ":" << *id_size << std::endl;
+  if (NULL == *result)
+  {
+    *size = 0;
+  }
+  if (NULL == sid)
+  {
+    *id_size = 0;
+  }
+  else
+  {
+    *id = (char *)malloc(sizeof(char) * ((*id_size) + 1));
+    if (NULL == *id)
+    {
+      *id_size = 0;
+    }
+    else
+    {
+      memset(*id, 0, (*id_size) + 1);
+      memcpy(*id, sid, *id_size);
+    }
+    free(sid);
+  }
+  std::cout << "[c++ bridge] findPath out: " << ret << ":" << *size << ":" << *id_size << std::endl;
 
   return ret;
 }
 
+void freeBuffer(void *buffer)
+{
+  // the allocator contract is described in library-bridge.hpp
+  free(buffer);
+}
+
 // int findGuide(const char *condition, unsigned char **result, unsigned int *size)
 // {
 //   std::cout << "[c++ bridge] findGuide in: " << condition << std::endl;
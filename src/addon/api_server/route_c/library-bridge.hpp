@@ -6,7 +6,16 @@
 // {
 // #endif
 int init(const char* path);
+// result and id are NULL with a zero size when not produced, both must be
+// released with freeBuffer
 int findPath(const unsigned char *condition, unsigned int conditionSize, unsigned char **result, unsigned int *size, char **id, unsigned int *id_size, unsigned int format);
+// releases a buffer returned by findPath, NULL is ignored.
+// routing_server.h exports no release function, the routing library
+// allocates routeBinData and routeID with malloc and leaves them to the
+// caller, the bridge has freed routeID with free since its first version.
+// The library must link the same C runtime as the server, the shared libc,
+// for free to release its buffers
+void freeBuffer(void *buffer);
 // int findGuide(const char *condition, unsigned char **result, unsigned int *size);
 // #ifdef __cplusplus
 // } // extern "C"
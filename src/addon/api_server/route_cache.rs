@@ -0,0 +1,366 @@
+use protobuf::Message;
+use std::error::Error;
+use std::fmt::Display;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use crate::config::RouteCacheConfig;
+use crate::protos::route_server_param::RoutePlanServerParameter;
+
+use super::memory_cache::MemoryKvCache;
+use super::redis_client::{RedisClientOperation, RedisHealth};
+
+/// Prefix of the keys of computed routes, followed by the route id
+const ROUTE_KEY_PREFIX: &str = "navi:route:";
+
+/// Prefix of the keys mapping a route request to the id of its route
+const PLAN_KEY_PREFIX: &str = "navi:plan:";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheError {
+  pub message: String,
+}
+
+impl Error for CacheError {}
+
+impl Display for CacheError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.message)
+  }
+}
+
+impl From<redis::RedisError> for CacheError {
+  fn from(error: redis::RedisError) -> Self {
+    CacheError {
+      message: error.to_string(),
+    }
+  }
+}
+
+/// State of a cache backend reported by the health endpoint
+#[derive(Debug, Clone, PartialEq)]
+pub enum CacheHealth {
+  Up,
+  Down(String),
+  /// The backend is not configured, nothing is cached
+  Disabled,
+}
+
+impl From<RedisHealth> for CacheHealth {
+  fn from(health: RedisHealth) -> Self {
+    match health {
+      RedisHealth::Up => CacheHealth::Up,
+      RedisHealth::Down(reason) => CacheHealth::Down(reason),
+      RedisHealth::Disabled => CacheHealth::Disabled,
+    }
+  }
+}
+
+pub type CacheFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, CacheError>> + Send + 'a>>;
+
+pub type HealthFuture<'a> = Pin<Box<dyn Future<Output = CacheHealth> + Send + 'a>>;
+
+/// Key value store backing the route and POI caches
+pub trait KvCache: std::fmt::Debug + Send + Sync {
+  /// Name of the backend reported by the health endpoint
+  fn backend(&self) -> &'static str;
+
+  fn get<'a>(&'a self, key: &'a str) -> CacheFuture<'a, Option<Vec<u8>>>;
+
+  /// Stores `value` under `key`, expiring after `ttl` seconds unless `ttl`
+  /// is `0`
+  fn set<'a>(&'a self, key: &'a str, value: &'a [u8], ttl: u64) -> CacheFuture<'a, ()>;
+
+  fn health(&self) -> HealthFuture<'_>;
+}
+
+/// `KvCache` on Redis, shared by every server
+#[derive(Debug, Default)]
+pub struct RedisKvCache {
+  redis_client: RedisClientOperation,
+}
+
+impl RedisKvCache {
+  pub fn new(redis_client: RedisClientOperation) -> RedisKvCache {
+    RedisKvCache { redis_client }
+  }
+}
+
+impl KvCache for RedisKvCache {
+  fn backend(&self) -> &'static str {
+    "redis"
+  }
+
+  fn get<'a>(&'a self, key: &'a str) -> CacheFuture<'a, Option<Vec<u8>>> {
+    Box::pin(async move { Ok(self.redis_client.get(key).await?) })
+  }
+
+  fn set<'a>(&'a self, key: &'a str, value: &'a [u8], ttl: u64) -> CacheFuture<'a, ()> {
+    Box::pin(async move { Ok(self.redis_client.set(key, value, ttl).await?) })
+  }
+
+  fn health(&self) -> HealthFuture<'_> {
+    Box::pin(async move { CacheHealth::from(self.redis_client.health().await) })
+  }
+}
+
+/// The `memory` backend limited to `max_entries` and `max_bytes`, or the
+/// `redis` backend. Fails on any other backend name
+pub fn make_cache(
+  backend: &str,
+  redis_client: &RedisClientOperation,
+  max_entries: usize,
+  max_bytes: usize,
+) -> Result<Arc<dyn KvCache>, String> {
+  match backend {
+    "memory" => Ok(Arc::new(MemoryKvCache::new(max_entries, max_bytes))),
+    "redis" => Ok(Arc::new(RedisKvCache::new(redis_client.clone()))),
+    backend => Err(format!("unknown cache backend {}", backend)),
+  }
+}
+
+/// Computed routes kept in a `KvCache`.
+///
+/// Routes are stored under `navi:route:` and their route id, so
+/// `GET /api/v1/navi/:id` cannot read other keys of the cache. Requests
+/// are also mapped to the id of the route computed for them, keyed by a
+/// hash of the encoded `RoutePlanServerParameter`, so identical requests
+/// skip the routing library
+#[derive(Debug, Clone)]
+pub struct CachedRoutes {
+  cache: Arc<dyn KvCache>,
+  config: RouteCacheConfig,
+}
+
+impl Default for CachedRoutes {
+  fn default() -> Self {
+    CachedRoutes::new(Arc::new(RedisKvCache::default()), Default::default())
+  }
+}
+
+impl CachedRoutes {
+  pub fn new(cache: Arc<dyn KvCache>, config: RouteCacheConfig) -> CachedRoutes {
+    CachedRoutes { cache, config }
+  }
+
+  /// Uses the backend chosen by `routeCache.backend`
+  pub fn from_config(
+    redis_client: &RedisClientOperation,
+    config: RouteCacheConfig,
+  ) -> Result<CachedRoutes, String> {
+    let cache = make_cache(
+      &config.backend,
+      redis_client,
+      config.max_entries,
+      config.max_bytes,
+    )
+    .map_err(|error| format!("routeCache: {}", error))?;
+    Ok(CachedRoutes::new(cache, config))
+  }
+
+  pub fn backend(&self) -> &'static str {
+    self.cache.backend()
+  }
+
+  pub async fn health(&self) -> CacheHealth {
+    self.cache.health().await
+  }
+
+  /// The protobuf encoded `RouteResult` stored for a route id
+  pub async fn get_route(&self, id: &str) -> Result<Option<Vec<u8>>, CacheError> {
+    self.cache.get(&route_key(id)).await
+  }
+
+  /// Finds the route computed for an identical request, returning its id
+  /// and its protobuf encoded `RouteResult`
+  pub async fn lookup_plan(
+    &self,
+    condition: &[u8],
+  ) -> Result<Option<(String, Vec<u8>)>, CacheError> {
+    let key = match self.plan_key(condition) {
+      Some(key) => key,
+      None => return Ok(None),
+    };
+    let id = match self.cache.get(&key).await? {
+      Some(id) => String::from_utf8_lossy(&id).into_owned(),
+      None => return Ok(None),
+    };
+    Ok(self.get_route(&id).await?.map(|route| (id, route)))
+  }
+
+  /// Stores a route computed for `condition` under its id and maps the
+  /// request to it
+  pub async fn save_plan(
+    &self,
+    condition: &[u8],
+    id: &str,
+    route: &[u8],
+  ) -> Result<(), CacheError> {
+    if id.is_empty() {
+      return Ok(());
+    }
+    self
+      .cache
+      .set(&route_key(id), route, self.config.route_ttl)
+      .await?;
+    if let Some(key) = self.plan_key(condition) {
+      self.cache.set(&key, id.as_bytes(), self.plan_ttl()).await?;
+    }
+    Ok(())
+  }
+
+  /// A request must not outlive the route it points to
+  fn plan_ttl(&self) -> u64 {
+    match self.config.route_ttl {
+      0 => self.config.plan_ttl,
+      route_ttl => std::cmp::min(route_ttl, self.config.plan_ttl),
+    }
+  }
+
+  /// The key of a route request, `None` when routes must not be shared
+  /// between requests, which is when the lookup is disabled or the route
+  /// depends on real time traffic
+  fn plan_key(&self, condition: &[u8]) -> Option<String> {
+    if 0 == self.config.plan_ttl {
+      return None;
+    }
+    let params = RoutePlanServerParameter::parse_from_bytes(condition).ok()?;
+    if params.realTimeTraffic {
+      return None;
+    }
+    Some(format!("{}{:032x}", PLAN_KEY_PREFIX, fnv1a_128(condition)))
+  }
+}
+
+fn route_key(id: &str) -> String {
+  format!("{}{}", ROUTE_KEY_PREFIX, id)
+}
+
+/// 128-bit FNV-1a, stable across builds and platforms unlike the hasher
+/// of the standard library
+pub fn fnv1a_128(data: &[u8]) -> u128 {
+  const OFFSET_BASIS: u128 = 0x6c62272e07bb014262b821756295c58d;
+  const PRIME: u128 = 0x0000000001000000000000000000013b;
+
+  data.iter().fold(OFFSET_BASIS, |hash, byte| {
+    (hash ^ *byte as u128).wrapping_mul(PRIME)
+  })
+}
+
+#[cfg(test)]
+pub mod tests {
+  use super::*;
+
+  fn memory_cache() -> Arc<dyn KvCache> {
+    Arc::new(MemoryKvCache::new(0, 0))
+  }
+
+  fn make_condition(real_time_traffic: bool) -> Vec<u8> {
+    let mut params = RoutePlanServerParameter::new();
+    params.mode = 1;
+    params.realTimeTraffic = real_time_traffic;
+    params.middlePoiID.push(String::from("21"));
+    params.write_to_bytes().unwrap()
+  }
+
+  #[test]
+  fn hashes_with_fnv1a_128() {
+    assert_eq!(fnv1a_128(b""), 0x6c62272e07bb014262b821756295c58d);
+    assert_eq!(fnv1a_128(b"a"), 0xd228cb696f1a8caf78912b704e4a8964);
+  }
+
+  #[test]
+  fn keys_identical_requests_alike() {
+    let cache = CachedRoutes::default();
+    let key = cache.plan_key(&make_condition(false)).unwrap();
+
+    assert!(key.starts_with(PLAN_KEY_PREFIX));
+    assert_eq!(cache.plan_key(&make_condition(false)), Some(key));
+    assert_eq!(cache.plan_key(&make_condition(true)), None);
+  }
+
+  #[test]
+  fn plan_does_not_outlive_route() {
+    let mut config = RouteCacheConfig::default();
+    config.route_ttl = 60;
+    config.plan_ttl = 600;
+    assert_eq!(
+      CachedRoutes::new(memory_cache(), config.clone()).plan_ttl(),
+      60
+    );
+
+    config.route_ttl = 0;
+    assert_eq!(
+      CachedRoutes::new(memory_cache(), config.clone()).plan_ttl(),
+      600
+    );
+
+    config.plan_ttl = 0;
+    let cache = CachedRoutes::new(memory_cache(), config);
+    assert_eq!(cache.plan_key(&make_condition(false)), None);
+  }
+
+  #[tokio::test]
+  async fn serves_identical_requests_from_cache() {
+    let routes = CachedRoutes::new(memory_cache(), RouteCacheConfig::default());
+    let condition = make_condition(false);
+
+    assert_eq!(routes.lookup_plan(&condition).await, Ok(None));
+    routes
+      .save_plan(&condition, "1001", b"route")
+      .await
+      .unwrap();
+
+    assert_eq!(
+      routes.lookup_plan(&condition).await,
+      Ok(Some((String::from("1001"), b"route".to_vec())))
+    );
+    assert_eq!(routes.get_route("1001").await, Ok(Some(b"route".to_vec())));
+  }
+
+  #[tokio::test]
+  async fn serves_only_routes_by_id() {
+    let cache = memory_cache();
+    let routes = CachedRoutes::new(cache.clone(), RouteCacheConfig::default());
+    routes
+      .save_plan(&make_condition(false), "1001", b"route")
+      .await
+      .unwrap();
+    cache.set("secret", b"value", 0).await.unwrap();
+
+    assert_eq!(
+      cache.get("navi:route:1001").await,
+      Ok(Some(b"route".to_vec()))
+    );
+    assert_eq!(routes.get_route("secret").await, Ok(None));
+    let plan_key = routes.plan_key(&make_condition(false)).unwrap();
+    assert_eq!(routes.get_route(&plan_key).await, Ok(None));
+  }
+
+  #[test]
+  fn picks_backend_from_config() {
+    let redis_client = RedisClientOperation::default();
+    let mut config = RouteCacheConfig::default();
+    assert_eq!(
+      CachedRoutes::from_config(&redis_client, config.clone())
+        .unwrap()
+        .backend(),
+      "redis"
+    );
+
+    config.backend = String::from("memory");
+    assert_eq!(
+      CachedRoutes::from_config(&redis_client, config.clone())
+        .unwrap()
+        .backend(),
+      "memory"
+    );
+
+    config.backend = String::from("memroy");
+    assert_eq!(
+      CachedRoutes::from_config(&redis_client, config).err(),
+      Some(String::from("routeCache: unknown cache backend memroy"))
+    );
+  }
+}
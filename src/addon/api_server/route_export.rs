@@ -0,0 +1,222 @@
+use serde_json::{json, Map, Value};
+
+use crate::protos::route_result::{RoadDescribe, RoadPoint, RouteResult, WalkGuidePoint};
+
+fn position(point: &RoadPoint) -> Value {
+  json!([point.longitude, point.latitude])
+}
+
+fn describe_properties(route_index: usize, describe: &RoadDescribe) -> Map<String, Value> {
+  let mut properties = Map::new();
+  properties.insert(String::from("routeIndex"), json!(route_index));
+  // 64-bit ids are rendered as strings as in the protobuf canonical JSON mapping
+  properties.insert(String::from("routeID"), json!(describe.routeID.to_string()));
+  properties.insert(String::from("mode"), json!(describe.mode));
+  properties.insert(String::from("policy"), json!(describe.policy));
+  properties.insert(String::from("length"), json!(describe.length));
+  properties.insert(String::from("time"), json!(describe.time));
+  properties.insert(String::from("lightCount"), json!(describe.lightCount));
+  properties.insert(String::from("overPassCnt"), json!(describe.overPassCnt));
+  properties.insert(String::from("underPassCnt"), json!(describe.underPassCnt));
+  properties.insert(String::from("turningCnt"), json!(describe.turningCnt));
+  properties.insert(String::from("fee"), json!(describe.fee));
+  properties
+}
+
+fn guide_properties(
+  route_index: usize,
+  guide_index: usize,
+  guide_point: &WalkGuidePoint,
+) -> Map<String, Value> {
+  let mut properties = Map::new();
+  properties.insert(String::from("routeIndex"), json!(route_index));
+  properties.insert(String::from("guideIndex"), json!(guide_index));
+  properties.insert(String::from("inLinkForm"), json!(guide_point.inLinkForm));
+  properties.insert(String::from("outLinkForm"), json!(guide_point.outLinkForm));
+  properties.insert(String::from("inLinkClass"), json!(guide_point.inLinkClass));
+  properties.insert(
+    String::from("outLinkClass"),
+    json!(guide_point.outLinkClass),
+  );
+  properties.insert(String::from("guideType"), json!(guide_point.guideType));
+  properties.insert(
+    String::from("outlinkID"),
+    json!(guide_point.outlinkID.to_string()),
+  );
+  properties.insert(String::from("light"), json!(guide_point.light));
+  properties.insert(
+    String::from("trafficLight"),
+    json!(guide_point.trafficLight),
+  );
+  properties.insert(String::from("realNode"), json!(guide_point.realNode));
+  properties.insert(
+    String::from("realNodeGroup"),
+    json!(guide_point.realNodeGroup),
+  );
+  properties.insert(
+    String::from("inLinkOrient"),
+    json!(guide_point.inLinkOrient),
+  );
+  properties.insert(
+    String::from("outLinkOrient"),
+    json!(guide_point.outLinkOrient),
+  );
+  properties.insert(String::from("angle"), json!(guide_point.angle));
+  properties.insert(String::from("inLinkLen"), json!(guide_point.inLinkLen));
+  properties.insert(String::from("outLinkLen"), json!(guide_point.outLinkLen));
+  properties.insert(String::from("inLinkName"), json!(guide_point.inLinkName));
+  properties.insert(String::from("outLinkName"), json!(guide_point.outLinkName));
+  properties
+}
+
+/// Exports a `RouteResult` as a GeoJSON `FeatureCollection`.
+///
+/// Every `Route` becomes a `LineString` feature of its `roadPoints` with
+/// the `RoadDescribe` as properties, followed by a `Point` feature for each
+/// of its `guidePoints` with the `WalkGuidePoint` fields as properties
+pub fn to_geojson(route_result: &RouteResult) -> Value {
+  let mut features = Vec::<Value>::new();
+
+  for (route_index, route) in route_result.routes.iter().enumerate() {
+    features.push(json!({
+      "type": "Feature",
+      "geometry": {
+        "type": "LineString",
+        "coordinates": route.roadPoints.iter().map(position).collect::<Vec<Value>>(),
+      },
+      "properties": describe_properties(route_index, route.get_describe()),
+    }));
+
+    for (guide_index, guide_point) in route.guidePoints.iter().enumerate() {
+      features.push(json!({
+        "type": "Feature",
+        "geometry": {
+          "type": "Point",
+          "coordinates": position(guide_point.get_guidePoint()),
+        },
+        "properties": guide_properties(route_index, guide_index, guide_point),
+      }));
+    }
+  }
+
+  json!({
+    "type": "FeatureCollection",
+    "features": features,
+  })
+}
+
+fn escape_xml(value: &str) -> String {
+  value
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+    .replace('\'', "&apos;")
+}
+
+/// Exports a `RouteResult` as a GPX 1.1 document.
+///
+/// Guide points are exported as waypoints named after their out link and
+/// every `Route` as a track of its `roadPoints`
+pub fn to_gpx(route_result: &RouteResult) -> String {
+  let mut gpx = String::from(
+    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+     <gpx version=\"1.1\" creator=\"http-server\" xmlns=\"http://www.topografix.com/GPX/1/1\">\n",
+  );
+
+  for route in route_result.routes.iter() {
+    for guide_point in route.guidePoints.iter() {
+      let point = guide_point.get_guidePoint();
+      gpx += &format!(
+        "  <wpt lat=\"{}\" lon=\"{}\">\n    <ele>{}</ele>\n    <name>{}</name>\n    <type>{}</type>\n  </wpt>\n",
+        point.latitude,
+        point.longitude,
+        point.height,
+        escape_xml(&guide_point.outLinkName),
+        guide_point.guideType,
+      );
+    }
+  }
+
+  for route in route_result.routes.iter() {
+    let describe = route.get_describe();
+    gpx += &format!(
+      "  <trk>\n    <name>{}</name>\n    <desc>length={} time={} lightCount={} turningCnt={}</desc>\n    <trkseg>\n",
+      describe.routeID, describe.length, describe.time, describe.lightCount, describe.turningCnt,
+    );
+    for point in route.roadPoints.iter() {
+      gpx += &format!(
+        "      <trkpt lat=\"{}\" lon=\"{}\">\n        <ele>{}</ele>\n      </trkpt>\n",
+        point.latitude, point.longitude, point.height,
+      );
+    }
+    gpx += "    </trkseg>\n  </trk>\n";
+  }
+
+  gpx += "</gpx>\n";
+  gpx
+}
+
+#[cfg(test)]
+pub mod tests {
+  use super::*;
+  use crate::protos::route_result::Route;
+
+  fn make_route_result() -> RouteResult {
+    let mut route = Route::new();
+    {
+      let describe = route.mut_describe();
+      describe.routeID = 42;
+      describe.length = 120;
+      describe.time = 90;
+      describe.lightCount = 1;
+      describe.turningCnt = 2;
+    }
+    for (longitude, latitude) in vec![(116.44, 39.90), (116.45, 39.91)] {
+      let mut point = RoadPoint::new();
+      point.longitude = longitude;
+      point.latitude = latitude;
+      route.roadPoints.push(point);
+    }
+    let mut guide_point = WalkGuidePoint::new();
+    guide_point.mut_guidePoint().longitude = 116.45;
+    guide_point.mut_guidePoint().latitude = 39.91;
+    guide_point.guideType = 3;
+    guide_point.outLinkName = String::from("A & B");
+    route.guidePoints.push(guide_point);
+
+    let mut route_result = RouteResult::new();
+    route_result.routes.push(route);
+    route_result
+  }
+
+  #[test]
+  fn exports_geojson_features() {
+    let geojson = to_geojson(&make_route_result());
+    let features = geojson["features"].as_array().unwrap();
+
+    assert_eq!(geojson["type"], "FeatureCollection");
+    assert_eq!(features.len(), 2);
+    assert_eq!(features[0]["geometry"]["type"], "LineString");
+    assert_eq!(
+      features[0]["geometry"]["coordinates"],
+      json!([[116.44, 39.90], [116.45, 39.91]])
+    );
+    assert_eq!(features[0]["properties"]["routeID"], "42");
+    assert_eq!(features[0]["properties"]["length"], 120);
+    assert_eq!(features[0]["properties"]["turningCnt"], 2);
+    assert_eq!(features[1]["geometry"]["type"], "Point");
+    assert_eq!(features[1]["properties"]["guideType"], 3);
+    assert_eq!(features[1]["properties"]["outLinkName"], "A & B");
+  }
+
+  #[test]
+  fn exports_gpx_tracks_and_waypoints() {
+    let gpx = to_gpx(&make_route_result());
+
+    assert!(gpx.contains("<wpt lat=\"39.91\" lon=\"116.45\">"));
+    assert!(gpx.contains("<name>A &amp; B</name>"));
+    assert!(gpx.contains("<trkpt lat=\"39.9\" lon=\"116.44\">"));
+    assert!(gpx.find("<wpt").unwrap() < gpx.find("<trk>").unwrap());
+  }
+}
@@ -0,0 +1,312 @@
+use http::header::ACCEPT;
+use hyper::{Body, Request};
+use protobuf::Message;
+
+use crate::protos::route_result::RouteResult;
+
+use super::error::ApiError;
+use super::floor_legs;
+use super::guidance::Guidance;
+use super::proto_json;
+use super::route_export;
+
+/// Representation of a computed route sent back to clients
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RouteFormat {
+  /// The binary route as produced by the routing library
+  Native,
+  /// A protobuf encoded `RouteResult`
+  Protobuf,
+  /// A `RouteResult` in the protobuf canonical JSON mapping, each route
+  /// carrying its `floorLegs` and turn-by-turn `instructions`
+  Json,
+  /// A GeoJSON `FeatureCollection` of the route lines and guide points
+  GeoJson,
+  /// A GPX document with a track per route and a waypoint per guide point
+  Gpx,
+}
+
+impl RouteFormat {
+  /// Picks the format from the `format` query parameter, otherwise from
+  /// the known media type with the highest `q` weight on the `Accept`
+  /// header, the first of them on a tie, otherwise defaults to
+  /// `RouteFormat::Protobuf`. Media types weighted `q=0` are never picked,
+  /// the request is not acceptable when that excludes the default
+  pub fn from_request(request: &Request<Body>) -> Result<RouteFormat, ApiError> {
+    if let Some(format) = query_param(request.uri().query().unwrap_or_default(), "format") {
+      return RouteFormat::from_name(&format)
+        .ok_or_else(|| ApiError::BadInput(format!("unknown route format: {}", format)));
+    }
+
+    let accept = request
+      .headers()
+      .get(ACCEPT)
+      .and_then(|value| value.to_str().ok())
+      .unwrap_or_default();
+
+    let mut best: Option<(RouteFormat, f32)> = None;
+    let mut excluded = Vec::new();
+    for (media_type, weight) in media_ranges(accept) {
+      let format = match RouteFormat::from_content_type(media_type) {
+        Some(format) => format,
+        None => continue,
+      };
+      if 0.0 >= weight {
+        excluded.push(format);
+      } else if !matches!(best, Some((_, best_weight)) if best_weight >= weight) {
+        best = Some((format, weight));
+      }
+    }
+    match best {
+      Some((format, _)) => Ok(format),
+      None if excluded.contains(&RouteFormat::Protobuf) => {
+        Err(ApiError::NotAcceptable(String::from(accept)))
+      }
+      None => Ok(RouteFormat::Protobuf),
+    }
+  }
+
+  fn from_name(name: &str) -> Option<RouteFormat> {
+    match name {
+      "native" => Some(RouteFormat::Native),
+      "protobuf" => Some(RouteFormat::Protobuf),
+      "json" => Some(RouteFormat::Json),
+      "geojson" => Some(RouteFormat::GeoJson),
+      "gpx" => Some(RouteFormat::Gpx),
+      _ => None,
+    }
+  }
+
+  fn from_content_type(content_type: &str) -> Option<RouteFormat> {
+    match content_type {
+      "application/octet-stream" => Some(RouteFormat::Native),
+      "application/x-protobuf" => Some(RouteFormat::Protobuf),
+      "application/json" => Some(RouteFormat::Json),
+      "application/geo+json" => Some(RouteFormat::GeoJson),
+      "application/gpx+xml" => Some(RouteFormat::Gpx),
+      _ => None,
+    }
+  }
+
+  pub fn content_type(&self) -> &'static str {
+    match self {
+      RouteFormat::Native => "application/octet-stream",
+      RouteFormat::Protobuf => "application/x-protobuf",
+      RouteFormat::Json => "application/json",
+      RouteFormat::GeoJson => "application/geo+json",
+      RouteFormat::Gpx => "application/gpx+xml",
+    }
+  }
+
+  /// Renders the route produced by the routing engine, native for
+  /// `RouteFormat::Native` and a protobuf encoded `RouteResult` otherwise,
+  /// into this format, instructions are written in the `locale` phrases of
+  /// `guidance`
+  pub fn render(
+    &self,
+    payload: Vec<u8>,
+    guidance: &Guidance,
+    locale: &str,
+  ) -> Result<Vec<u8>, ApiError> {
+    match self {
+      RouteFormat::Native => Ok(payload),
+      RouteFormat::Protobuf => {
+        let route_result = parse_route_result(&payload)?;
+        route_result
+          .write_to_bytes()
+          .map_err(|error| ApiError::InvalidRoute(error.to_string()))
+      }
+      RouteFormat::Json => {
+        let route_result = parse_route_result(&payload)?;
+        let mut json = proto_json::to_json(&route_result);
+        let has_locale = guidance.has_locale(locale);
+        if let Some(routes) = json["routes"].as_array_mut() {
+          for (route, route_json) in route_result.routes.iter().zip(routes.iter_mut()) {
+            route_json["floorLegs"] = floor_legs::floor_legs(route)
+              .iter()
+              .map(floor_legs::FloorLeg::to_json)
+              .collect();
+            if has_locale {
+              route_json["instructions"] =
+                serde_json::json!(guidance.route_instructions(locale, route));
+            }
+          }
+        }
+        Ok(json.to_string().into_bytes())
+      }
+      RouteFormat::GeoJson => {
+        let route_result = parse_route_result(&payload)?;
+        Ok(
+          route_export::to_geojson(&route_result)
+            .to_string()
+            .into_bytes(),
+        )
+      }
+      RouteFormat::Gpx => {
+        let route_result = parse_route_result(&payload)?;
+        Ok(route_export::to_gpx(&route_result).into_bytes())
+      }
+    }
+  }
+}
+
+pub fn parse_route_result(payload: &[u8]) -> Result<RouteResult, ApiError> {
+  RouteResult::parse_from_bytes(payload).map_err(|error| ApiError::InvalidRoute(error.to_string()))
+}
+
+/// Media types of an `Accept` header with their `q` weight, `1` when not
+/// given. Media ranges with an invalid weight are left out
+fn media_ranges(accept: &str) -> impl Iterator<Item = (&str, f32)> {
+  accept.split(',').filter_map(|media_range| {
+    let mut parts = media_range.split(';').map(str::trim);
+    let media_type = parts.next()?;
+    let weight = parts
+      .find_map(|param| param.strip_prefix("q="))
+      .map_or(Some(1.0), |weight| weight.parse::<f32>().ok())?;
+    Some((media_type, weight))
+  })
+}
+
+/// The percent-decoded value of the query parameter `name`
+pub fn query_param(query: &str, name: &str) -> Option<String> {
+  form_urlencoded::parse(query.as_bytes())
+    .find(|(key, _)| key == name)
+    .map(|(_, value)| value.into_owned())
+}
+
+#[cfg(test)]
+pub mod tests {
+  use super::*;
+  use crate::protos::route_result::{Route, WalkGuidePoint};
+
+  fn make_request(uri: &str, accept: Option<&str>) -> Request<Body> {
+    let mut builder = Request::builder().uri(uri);
+    if let Some(accept) = accept {
+      builder = builder.header(ACCEPT, accept);
+    }
+    builder.body(Body::empty()).unwrap()
+  }
+
+  #[test]
+  fn picks_format_from_query_then_accept() {
+    let cases = vec![
+      ("/api/v1/navi", None, RouteFormat::Protobuf),
+      ("/api/v1/navi", Some("*/*"), RouteFormat::Protobuf),
+      (
+        "/api/v1/navi",
+        Some("text/html, application/json;q=0.9"),
+        RouteFormat::Json,
+      ),
+      (
+        "/api/v1/navi",
+        Some("application/octet-stream"),
+        RouteFormat::Native,
+      ),
+      (
+        "/api/v1/navi?format=json",
+        Some("application/x-protobuf"),
+        RouteFormat::Json,
+      ),
+      ("/api/v1/navi?a=1&format=native", None, RouteFormat::Native),
+      ("/api/v1/navi?format=geojson", None, RouteFormat::GeoJson),
+      ("/api/v1/navi?format=%67px", None, RouteFormat::Gpx),
+      (
+        "/api/v1/navi",
+        Some("application/json;q=0.5, application/gpx+xml"),
+        RouteFormat::Gpx,
+      ),
+      (
+        "/api/v1/navi",
+        Some("application/json;q=0, application/geo+json;q=0.2"),
+        RouteFormat::GeoJson,
+      ),
+      (
+        "/api/v1/navi",
+        Some("application/json;q=0"),
+        RouteFormat::Protobuf,
+      ),
+      (
+        "/api/v1/navi",
+        Some("application/gpx+xml"),
+        RouteFormat::Gpx,
+      ),
+    ];
+
+    for (uri, accept, format) in cases {
+      let request = make_request(uri, accept);
+      assert_eq!(
+        RouteFormat::from_request(&request).unwrap(),
+        format,
+        "{}",
+        uri
+      );
+    }
+  }
+
+  #[test]
+  fn rejects_unknown_format() {
+    let request = make_request("/api/v1/navi?format=xml", None);
+
+    assert!(RouteFormat::from_request(&request).is_err());
+  }
+
+  #[test]
+  fn rejects_excluded_default_format() {
+    let request = make_request("/api/v1/navi", Some("application/x-protobuf;q=0"));
+
+    assert!(matches!(
+      RouteFormat::from_request(&request),
+      Err(ApiError::NotAcceptable(_))
+    ));
+  }
+
+  #[test]
+  fn decodes_query_params() {
+    assert_eq!(
+      query_param("a=1&lang=zh%2DCN&b", "lang"),
+      Some(String::from("zh-CN"))
+    );
+    assert_eq!(query_param("q=a+b", "q"), Some(String::from("a b")));
+    assert_eq!(query_param("a=1", "lang"), None);
+  }
+
+  #[test]
+  fn renders_route_result_as_json() {
+    let mut route_result = RouteResult::new();
+    route_result.version = 1;
+    let mut route = Route::new();
+    route.mut_describe().length = 120;
+    route_result.routes.push(route);
+    let payload = route_result.write_to_bytes().unwrap();
+
+    let json = RouteFormat::Json
+      .render(payload, &Guidance::default(), "en")
+      .unwrap();
+    let json = serde_json::from_slice::<serde_json::Value>(&json).unwrap();
+
+    assert_eq!(json["version"], 1);
+    assert_eq!(json["routes"][0]["describe"]["length"], 120);
+    assert_eq!(json["routes"][0]["floorLegs"], serde_json::json!([]));
+    assert!(json["routes"][0]["instructions"].is_null());
+  }
+
+  #[test]
+  fn renders_instructions_in_json() {
+    let mut route_result = RouteResult::new();
+    let mut route = Route::new();
+    let mut guide_point = WalkGuidePoint::new();
+    guide_point.guideType = 3;
+    route.guidePoints.push(guide_point);
+    route_result.routes.push(route);
+    let payload = route_result.write_to_bytes().unwrap();
+    let guidance = Guidance::load("routinglib/config/config.json");
+
+    let json = RouteFormat::Json.render(payload, &guidance, "en").unwrap();
+    let json = serde_json::from_slice::<serde_json::Value>(&json).unwrap();
+
+    assert_eq!(
+      json["routes"][0]["instructions"],
+      serde_json::json!(["Turn left"])
+    );
+  }
+}
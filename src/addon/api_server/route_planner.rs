@@ -0,0 +1,287 @@
+use serde_json::{json, Value};
+use std::error::Error;
+use std::fmt::Display;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+
+use crate::config::PlannerConfig;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlannerError {
+  /// Every thread is busy and the queue is full, holds the seconds to
+  /// wait before trying again
+  Saturated(u64),
+  /// No route within the timeout, in milliseconds
+  TimedOut(u64),
+  /// The job panicked or the planner is shut down
+  Aborted,
+}
+
+impl Error for PlannerError {}
+
+impl Display for PlannerError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      PlannerError::Saturated(_) => write!(f, "route planner saturated"),
+      PlannerError::TimedOut(timeout) => write!(f, "route planning exceeded {} ms", timeout),
+      PlannerError::Aborted => write!(f, "route planning aborted"),
+    }
+  }
+}
+
+/// Counters of the route planner since the server started, durations are
+/// in microseconds
+#[derive(Debug, Default)]
+pub struct PlannerMetrics {
+  /// Jobs waiting for a thread
+  queued: AtomicU64,
+  /// Jobs run to completion
+  completed: AtomicU64,
+  /// Jobs which panicked
+  panicked: AtomicU64,
+  /// Jobs rejected as the queue was full
+  rejected: AtomicU64,
+  /// Jobs whose caller stopped waiting
+  timed_out: AtomicU64,
+  queue_wait_total: AtomicU64,
+  queue_wait_max: AtomicU64,
+  compute_total: AtomicU64,
+  compute_max: AtomicU64,
+}
+
+impl PlannerMetrics {
+  pub fn to_json(&self) -> Value {
+    let completed = self.completed.load(Ordering::Relaxed);
+    let panicked = self.panicked.load(Ordering::Relaxed);
+    // panicked jobs waited and computed too
+    let average = |total: &AtomicU64| match completed + panicked {
+      0 => 0.0,
+      ran => total.load(Ordering::Relaxed) as f64 / ran as f64 / 1000.0,
+    };
+    let max = |max: &AtomicU64| max.load(Ordering::Relaxed) as f64 / 1000.0;
+    json!({
+      "queued": self.queued.load(Ordering::Relaxed),
+      "completed": completed,
+      "panicked": panicked,
+      "rejected": self.rejected.load(Ordering::Relaxed),
+      "timedOut": self.timed_out.load(Ordering::Relaxed),
+      "queueWaitMs": { "avg": average(&self.queue_wait_total), "max": max(&self.queue_wait_max) },
+      "computeMs": { "avg": average(&self.compute_total), "max": max(&self.compute_max) },
+    })
+  }
+
+  fn record(total: &AtomicU64, max: &AtomicU64, duration: Duration) {
+    let micros = duration.as_micros() as u64;
+    total.fetch_add(micros, Ordering::Relaxed);
+    max.fetch_max(micros, Ordering::Relaxed);
+  }
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Dedicated threads running the blocking routing library, so a slow
+/// route never holds up the async workers.
+///
+/// Jobs wait in a queue of `queue_depth` while every thread is busy and
+/// are rejected once it is full. A caller waits `timeout` at most, a job
+/// whose caller stopped waiting before it started is skipped
+#[derive(Clone)]
+pub struct RoutePlanner {
+  jobs: SyncSender<(Instant, Job)>,
+  config: PlannerConfig,
+  metrics: Arc<PlannerMetrics>,
+}
+
+impl std::fmt::Debug for RoutePlanner {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("RoutePlanner")
+      .field("config", &self.config)
+      .finish()
+  }
+}
+
+impl Default for RoutePlanner {
+  fn default() -> Self {
+    RoutePlanner::new(PlannerConfig::default())
+  }
+}
+
+impl RoutePlanner {
+  /// Starts the threads, which stop once every clone of the planner is
+  /// dropped
+  pub fn new(config: PlannerConfig) -> RoutePlanner {
+    let (jobs, queue) = mpsc::sync_channel::<(Instant, Job)>(config.queue_depth);
+    let queue = Arc::new(Mutex::new(queue));
+    let metrics = Arc::new(PlannerMetrics::default());
+    for worker in 0..std::cmp::max(config.workers, 1) {
+      let queue = Arc::clone(&queue);
+      let metrics = Arc::clone(&metrics);
+      thread::Builder::new()
+        .name(format!("route-planner-{}", worker))
+        .spawn(move || RoutePlanner::work(queue, metrics))
+        .expect("route planner thread");
+    }
+    RoutePlanner {
+      jobs,
+      config,
+      metrics,
+    }
+  }
+
+  pub fn metrics(&self) -> &PlannerMetrics {
+    &self.metrics
+  }
+
+  fn work(queue: Arc<Mutex<Receiver<(Instant, Job)>>>, metrics: Arc<PlannerMetrics>) {
+    loop {
+      let next = queue.lock().expect("route planner queue").recv();
+      let (queued_at, job) = match next {
+        Ok(next) => next,
+        Err(_) => return,
+      };
+      metrics.queued.fetch_sub(1, Ordering::Relaxed);
+      PlannerMetrics::record(
+        &metrics.queue_wait_total,
+        &metrics.queue_wait_max,
+        queued_at.elapsed(),
+      );
+      job();
+    }
+  }
+
+  /// Runs `job` on a planner thread
+  pub async fn run<T, F>(&self, job: F) -> Result<T, PlannerError>
+  where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+  {
+    let (sender, receiver) = oneshot::channel::<T>();
+    let metrics = Arc::clone(&self.metrics);
+    let task: Job = Box::new(move || {
+      if sender.is_closed() {
+        return;
+      }
+      let started_at = Instant::now();
+      let ret = panic::catch_unwind(AssertUnwindSafe(job));
+      PlannerMetrics::record(
+        &metrics.compute_total,
+        &metrics.compute_max,
+        started_at.elapsed(),
+      );
+      // a panic drops the sender, the caller sees the job aborted
+      match ret {
+        Ok(ret) => {
+          metrics.completed.fetch_add(1, Ordering::Relaxed);
+          let _ = sender.send(ret);
+        }
+        Err(_) => {
+          metrics.panicked.fetch_add(1, Ordering::Relaxed);
+        }
+      }
+    });
+
+    self.metrics.queued.fetch_add(1, Ordering::Relaxed);
+    match self.jobs.try_send((Instant::now(), task)) {
+      Ok(()) => (),
+      Err(TrySendError::Full(_)) => {
+        self.metrics.queued.fetch_sub(1, Ordering::Relaxed);
+        self.metrics.rejected.fetch_add(1, Ordering::Relaxed);
+        return Err(PlannerError::Saturated(self.config.retry_after));
+      }
+      Err(TrySendError::Disconnected(_)) => {
+        self.metrics.queued.fetch_sub(1, Ordering::Relaxed);
+        return Err(PlannerError::Aborted);
+      }
+    }
+
+    if 0 == self.config.timeout {
+      return receiver.await.map_err(|_| PlannerError::Aborted);
+    }
+    match tokio::time::timeout(Duration::from_millis(self.config.timeout), receiver).await {
+      Ok(ret) => ret.map_err(|_| PlannerError::Aborted),
+      Err(_) => {
+        self.metrics.timed_out.fetch_add(1, Ordering::Relaxed);
+        Err(PlannerError::TimedOut(self.config.timeout))
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+pub mod tests {
+  use super::*;
+
+  fn make_planner(workers: usize, queue_depth: usize, timeout: u64) -> RoutePlanner {
+    RoutePlanner::new(PlannerConfig {
+      workers,
+      queue_depth,
+      timeout,
+      retry_after: 1,
+    })
+  }
+
+  #[tokio::test]
+  async fn runs_jobs_off_the_runtime() {
+    let planner = make_planner(2, 4, 1000);
+    let ret = planner
+      .run(|| thread::current().name().map(String::from))
+      .await;
+
+    assert!(ret.unwrap().unwrap().starts_with("route-planner-"));
+    assert_eq!(planner.metrics().to_json()["completed"], 1);
+  }
+
+  #[tokio::test]
+  async fn rejects_when_saturated() {
+    let planner = make_planner(1, 1, 0);
+    let (release, hold) = mpsc::channel::<()>();
+    let (started, wait_started) = oneshot::channel::<()>();
+    let running = tokio::spawn({
+      let planner = planner.clone();
+      async move {
+        planner
+          .run(move || {
+            started.send(()).unwrap();
+            hold.recv().unwrap();
+          })
+          .await
+      }
+    });
+    wait_started.await.unwrap();
+    let queued = tokio::spawn({
+      let planner = planner.clone();
+      async move { planner.run(|| ()).await }
+    });
+    while 0 == planner.metrics().queued.load(Ordering::Relaxed) {
+      tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+
+    assert_eq!(planner.run(|| ()).await, Err(PlannerError::Saturated(1)));
+    assert_eq!(planner.metrics().to_json()["rejected"], 1);
+
+    release.send(()).unwrap();
+    running.await.unwrap().unwrap();
+    queued.await.unwrap().unwrap();
+  }
+
+  #[tokio::test]
+  async fn times_out_and_survives_panics() {
+    let planner = make_planner(1, 4, 50);
+    let ret = planner
+      .run(|| thread::sleep(Duration::from_millis(500)))
+      .await;
+    assert_eq!(ret, Err(PlannerError::TimedOut(50)));
+
+    let planner = make_planner(1, 4, 1000);
+    let ret = planner.run(|| -> i32 { panic!("engine") }).await;
+    assert_eq!(ret, Err(PlannerError::Aborted));
+    assert_eq!(planner.run(|| 7).await, Ok(7));
+    assert_eq!(planner.metrics().to_json()["panicked"], 1);
+    assert_eq!(planner.metrics().to_json()["completed"], 1);
+  }
+}
@@ -1,26 +1,51 @@
 #![allow(unused_assignments)]
 
-use std::error::Error;
+use protobuf::Message;
 use std::ffi::CString;
-use std::fmt::Display;
-use std::os::raw::{c_char, c_uchar, c_uint};
+use std::os::raw::{c_char, c_uchar, c_uint, c_void};
+
+use crate::protos::route_result::RouteResult;
+use crate::protos::route_server_param::RoutePlanServerParameter;
+
+use super::routing_engine::{RouteError, RoutingEngine};
 
 extern crate libc;
 
 include!(concat!(env!("OUT_DIR"), "/route.rs"));
 
-type RouteErrorCode = i32;
+/// `findPath` output format for the routing library native binary route
+pub const NATIVE_ROUTE_FORMAT: c_uint = 0;
 
-#[derive(Debug)]
-pub struct RouteError {
-  pub code: RouteErrorCode,
+/// `findPath` output format for a protobuf encoded `RouteResult`
+pub const PROTOBUF_ROUTE_FORMAT: c_uint = 1;
+
+/// Output buffer of `findPath`, owned by the routing library and released
+/// with `freeBuffer` when dropped. The library allocates it with `malloc`
+/// of the shared C runtime, see `library-bridge.hpp`
+struct NativeBuffer {
+  ptr: *mut c_void,
+  len: usize,
 }
 
-impl Error for RouteError {}
+impl NativeBuffer {
+  /// Copies the buffer into memory owned by Rust, `None` when it is null or
+  /// empty
+  fn to_vec(&self) -> Option<Vec<u8>> {
+    if self.ptr.is_null() || 0 == self.len {
+      return None;
+    }
+    // the library hands out `len` initialized bytes which stay valid until
+    // the buffer is released
+    let bytes = unsafe { std::slice::from_raw_parts(self.ptr as *const u8, self.len) };
+    Some(bytes.to_vec())
+  }
+}
 
-impl Display for RouteError {
-  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-    write!(f, "{:?}", self.code)
+impl Drop for NativeBuffer {
+  fn drop(&mut self) {
+    if !self.ptr.is_null() {
+      unsafe { freeBuffer(self.ptr) };
+    }
   }
 }
 
@@ -37,45 +62,90 @@ impl RouteWrapper {
     }
     return ret;
   }
-  pub fn find_path(condition: Vec<u8>) -> Result<(String, String), RouteError> {
-    // let ss = condition.as_ptr();
-    // let sss = condition.len();
-
-    // let condition = CString::new(condition).expect("condition");
-    // let condition_size: c_uint = 3;
-    let format: c_uint = 1;
-    let mut ret = 0;
-    let mut id: String = String::new();
-    let mut result = String::new();
-    unsafe {
-      let mut size = std::mem::zeroed::<c_uint>();
-      let mut id_size = std::mem::zeroed::<c_uint>();
-      let mut test_unsafe_result: *mut c_uchar = std::mem::zeroed();
-      std::mem::forget(test_unsafe_result);
-      let mut test_unsafe_id: *mut c_char = std::mem::zeroed();
-      std::mem::forget(test_unsafe_id);
-
-      ret = findPath(
-        condition.as_ptr() as *const u8,
-        condition.len() as u32,
-        &mut test_unsafe_result as *mut *mut c_uchar,
-        &mut size as *mut _,
-        &mut test_unsafe_id as *mut *mut c_char,
-        &mut id_size as *mut _,
+  /// Computes the route for an encoded `RoutePlanServerParameter`, returning
+  /// the route id and the route in `format`, both as bytes copied out of the
+  /// routing library
+  pub fn find_path(condition: &[u8], format: c_uint) -> Result<(Vec<u8>, Vec<u8>), RouteError> {
+    let mut result: *mut c_uchar = std::ptr::null_mut();
+    let mut size: c_uint = 0;
+    let mut id: *mut c_char = std::ptr::null_mut();
+    let mut id_size: c_uint = 0;
+    let ret = unsafe {
+      findPath(
+        condition.as_ptr(),
+        condition.len() as c_uint,
+        &mut result,
+        &mut size,
+        &mut id,
+        &mut id_size,
         format,
-      );
-      if 0 != ret {
-        return Err(RouteError { code: ret as i32 });
-      }
-      id = String::from_raw_parts(
-        test_unsafe_id as *mut u8,
-        id_size as usize,
-        id_size as usize,
-      );
-      result = String::from_raw_parts(test_unsafe_result as *mut u8, size as usize, size as usize);
+      )
+    };
+    // released on every path, including failures
+    let result = NativeBuffer {
+      ptr: result as *mut c_void,
+      len: size as usize,
+    };
+    let id = NativeBuffer {
+      ptr: id as *mut c_void,
+      len: id_size as usize,
+    };
+    if 0 != ret {
+      return Err(RouteError::Engine(ret as i32));
     }
-    println!("find_path->{}", ret);
-    return Ok((id, result));
+    let id = id.to_vec().ok_or(RouteError::EmptyOutput("route id"))?;
+    let result = result.to_vec().ok_or(RouteError::EmptyOutput("route"))?;
+    Ok((id, result))
+  }
+}
+
+/// `RoutingEngine` running the native routing library
+#[derive(Debug)]
+pub struct NativeEngine {}
+
+impl NativeEngine {
+  /// Initializes the routing library with the configuration and data in
+  /// `config_path`
+  pub fn new(config_path: &str) -> NativeEngine {
+    let ret = RouteWrapper::init(String::from(config_path));
+    println!("NativeEngine::new->{}", ret);
+    NativeEngine {}
+  }
+
+  fn plan(
+    params: &RoutePlanServerParameter,
+    format: c_uint,
+  ) -> Result<(String, Vec<u8>), RouteError> {
+    let condition = params
+      .write_to_bytes()
+      .map_err(|error| RouteError::InvalidParameter(error.to_string()))?;
+    let (id, route) = RouteWrapper::find_path(&condition, format)?;
+    let id = String::from_utf8(id)
+      .map_err(|_| RouteError::InvalidOutput(String::from("route id is not UTF-8")))?;
+    Ok((id, route))
+  }
+}
+
+impl RoutingEngine for NativeEngine {
+  fn name(&self) -> &'static str {
+    "native"
+  }
+
+  fn find_path(
+    &self,
+    params: &RoutePlanServerParameter,
+  ) -> Result<(String, RouteResult), RouteError> {
+    let (id, route) = NativeEngine::plan(params, PROTOBUF_ROUTE_FORMAT)?;
+    let route = RouteResult::parse_from_bytes(&route)
+      .map_err(|error| RouteError::InvalidOutput(error.to_string()))?;
+    Ok((id, route))
+  }
+
+  fn find_native_path(
+    &self,
+    params: &RoutePlanServerParameter,
+  ) -> Result<(String, Vec<u8>), RouteError> {
+    NativeEngine::plan(params, NATIVE_ROUTE_FORMAT)
   }
 }
 
@@ -92,7 +162,16 @@ pub mod tests {
   fn test_find_path() {
     use super::*;
     let condition = Vec::<u8>::new();
-    let ret = RouteWrapper::find_path(condition);
+    let ret = RouteWrapper::find_path(&condition, PROTOBUF_ROUTE_FORMAT);
     println!("test_find_path->{:#?}", ret);
   }
+  #[test]
+  fn null_buffers_are_empty() {
+    use super::*;
+    let buffer = NativeBuffer {
+      ptr: std::ptr::null_mut(),
+      len: 16,
+    };
+    assert_eq!(buffer.to_vec(), None);
+  }
 }
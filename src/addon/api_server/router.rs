@@ -0,0 +1,312 @@
+use futures::Future;
+use http::Method;
+use hyper::{Body, Request, Response};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use super::error::ApiError;
+use crate::utils::trace::UpstreamTraceIds;
+
+/// Named parameters captured from a path template, such as `id` for
+/// `/api/v1/navi/:id`
+pub type RouteParams = HashMap<String, String>;
+
+pub type RouteFuture = Pin<Box<dyn Future<Output = Result<Response<Body>, ApiError>> + Send>>;
+
+pub type RouteHandler<S> = Box<dyn Fn(Arc<S>, RouteRequest) -> RouteFuture + Send + Sync>;
+
+/// The HTTP request handed to a route handler together with the
+/// parameters captured from its path template, the request trace id and
+/// the trace ids of the upstream services called to serve it
+pub struct RouteRequest {
+  pub request: Arc<Mutex<Request<Body>>>,
+  pub params: RouteParams,
+  pub trace_id: String,
+  pub upstream_trace_ids: Arc<UpstreamTraceIds>,
+}
+
+impl RouteRequest {
+  pub fn param(&self, name: &str) -> Option<&str> {
+    self.params.get(name).map(|value| value.as_str())
+  }
+}
+
+/// A single segment of a path template.
+///
+/// A segment containing `:` captures the rest of the request segment under
+/// the name following the colon, the text before the colon and the colon
+/// itself must match literally. This allows both `/navi/:id` and
+/// `/navi:id`.
+#[derive(Debug, PartialEq)]
+enum Segment {
+  Literal(String),
+  Param { prefix: String, name: String },
+}
+
+impl Segment {
+  fn parse(segment: &str) -> Self {
+    match segment.find(':') {
+      Some(pos) => Segment::Param {
+        prefix: String::from(&segment[..pos]),
+        name: String::from(&segment[pos + 1..]),
+      },
+      None => Segment::Literal(String::from(segment)),
+    }
+  }
+
+  fn capture(&self, segment: &str, params: &mut RouteParams) -> bool {
+    match self {
+      Segment::Literal(literal) => literal == segment,
+      Segment::Param { prefix, name } => {
+        let value = if prefix.is_empty() {
+          Some(segment)
+        } else {
+          segment
+            .strip_prefix(prefix.as_str())
+            .and_then(|rest| rest.strip_prefix(':'))
+        };
+        match value {
+          Some(value) if !value.is_empty() => {
+            params.insert(name.clone(), String::from(value));
+            true
+          }
+          _ => false,
+        }
+      }
+    }
+  }
+}
+
+/// Splits a path into its segments ignoring leading and trailing slashes
+fn split_path(path: &str) -> Vec<&str> {
+  path
+    .trim_matches('/')
+    .split('/')
+    .filter(|segment| !segment.is_empty())
+    .collect()
+}
+
+struct Route<S> {
+  method: Method,
+  template: String,
+  segments: Vec<Segment>,
+  handler: RouteHandler<S>,
+}
+
+impl<S> Route<S> {
+  fn matches(&self, segments: &[&str]) -> Option<RouteParams> {
+    if self.segments.len() != segments.len() {
+      return None;
+    }
+
+    let mut params = RouteParams::new();
+
+    for (template, segment) in self.segments.iter().zip(segments.iter()) {
+      if !template.capture(segment, &mut params) {
+        return None;
+      }
+    }
+
+    Some(params)
+  }
+}
+
+/// Outcome of matching a request method and path against the `Router`
+pub enum RouteMatch<'a, S> {
+  /// A route matched both the path and the method
+  Found(&'a RouteHandler<S>, RouteParams),
+  /// One or more routes matched the path but none of them the method,
+  /// holds the methods to be advertised in the `Allow` header
+  MethodNotAllowed(Vec<Method>),
+  /// No route matched the path
+  NotFound,
+}
+
+/// Dispatches requests to handlers by HTTP method and path template.
+///
+/// Routes are tried in registration order, the first route matching both
+/// method and path wins.
+pub struct Router<S> {
+  routes: Vec<Route<S>>,
+}
+
+impl<S> Default for Router<S> {
+  fn default() -> Self {
+    Router { routes: Vec::new() }
+  }
+}
+
+impl<S> Debug for Router<S> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_list()
+      .entries(
+        self
+          .routes
+          .iter()
+          .map(|route| format!("{} {}", route.method, route.template)),
+      )
+      .finish()
+  }
+}
+
+impl<S> Router<S>
+where
+  S: Send + Sync + 'static,
+{
+  pub fn new() -> Self {
+    Router::default()
+  }
+
+  /// Registers a `handler` for the provided `method` and path `template`
+  pub fn add<F, Fut>(&mut self, method: Method, template: &str, handler: F) -> &mut Self
+  where
+    F: Fn(Arc<S>, RouteRequest) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<Response<Body>, ApiError>> + Send + 'static,
+  {
+    self.routes.push(Route {
+      method,
+      template: String::from(template),
+      segments: split_path(template)
+        .into_iter()
+        .map(Segment::parse)
+        .collect(),
+      handler: Box::new(move |state, request| Box::pin(handler(state, request))),
+    });
+
+    self
+  }
+
+  pub fn get<F, Fut>(&mut self, template: &str, handler: F) -> &mut Self
+  where
+    F: Fn(Arc<S>, RouteRequest) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<Response<Body>, ApiError>> + Send + 'static,
+  {
+    self.add(Method::GET, template, handler)
+  }
+
+  pub fn post<F, Fut>(&mut self, template: &str, handler: F) -> &mut Self
+  where
+    F: Fn(Arc<S>, RouteRequest) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<Response<Body>, ApiError>> + Send + 'static,
+  {
+    self.add(Method::POST, template, handler)
+  }
+
+  /// Finds the handler registered for the provided `method` and `path`
+  pub fn route(&self, method: &Method, path: &str) -> RouteMatch<'_, S> {
+    let segments = split_path(path);
+    let mut allowed = Vec::<Method>::new();
+
+    for route in self.routes.iter() {
+      if let Some(params) = route.matches(&segments) {
+        if route.method == *method {
+          return RouteMatch::Found(&route.handler, params);
+        }
+
+        if !allowed.contains(&route.method) {
+          allowed.push(route.method.clone());
+        }
+      }
+    }
+
+    if allowed.is_empty() {
+      RouteMatch::NotFound
+    } else {
+      RouteMatch::MethodNotAllowed(allowed)
+    }
+  }
+}
+
+#[cfg(test)]
+pub mod tests {
+  use super::*;
+
+  async fn ok(_: Arc<()>, _: RouteRequest) -> Result<Response<Body>, ApiError> {
+    Ok(Response::new(Body::empty()))
+  }
+
+  fn make_router() -> Router<()> {
+    let mut router = Router::<()>::new();
+
+    router
+      .get("/api/v1/health", ok)
+      .get("/api/v1/navi/:id", ok)
+      .get("/api/v1/navi:id", ok)
+      .post("/api/v1/navi", ok)
+      .post("/api/v1/navijson", ok);
+
+    router
+  }
+
+  #[test]
+  fn parses_template_segments() {
+    assert_eq!(
+      Segment::parse("navi"),
+      Segment::Literal(String::from("navi"))
+    );
+    assert_eq!(
+      Segment::parse(":id"),
+      Segment::Param {
+        prefix: String::new(),
+        name: String::from("id"),
+      }
+    );
+    assert_eq!(
+      Segment::parse("navi:id"),
+      Segment::Param {
+        prefix: String::from("navi"),
+        name: String::from("id"),
+      }
+    );
+  }
+
+  #[test]
+  fn matches_literal_paths_exactly() {
+    let router = make_router();
+
+    assert!(matches!(
+      router.route(&Method::POST, "/api/v1/navi"),
+      RouteMatch::Found(_, _)
+    ));
+    assert!(matches!(
+      router.route(&Method::POST, "/api/v1/navijson/"),
+      RouteMatch::Found(_, _)
+    ));
+    assert!(matches!(
+      router.route(&Method::POST, "/api/v1/navijsonx"),
+      RouteMatch::NotFound
+    ));
+    assert!(matches!(
+      router.route(&Method::GET, "/prefix/api/v1/health"),
+      RouteMatch::NotFound
+    ));
+  }
+
+  #[test]
+  fn captures_named_params() {
+    let router = make_router();
+
+    match router.route(&Method::GET, "/api/v1/navi/abc123") {
+      RouteMatch::Found(_, params) => assert_eq!(params.get("id").unwrap(), "abc123"),
+      _ => panic!("expected a route"),
+    }
+
+    match router.route(&Method::GET, "/api/v1/navi:abc123") {
+      RouteMatch::Found(_, params) => assert_eq!(params.get("id").unwrap(), "abc123"),
+      _ => panic!("expected a route"),
+    }
+  }
+
+  #[test]
+  fn reports_allowed_methods() {
+    let router = make_router();
+
+    match router.route(&Method::GET, "/api/v1/navi") {
+      RouteMatch::MethodNotAllowed(allowed) => assert_eq!(allowed, vec![Method::POST]),
+      _ => panic!("expected method not allowed"),
+    }
+  }
+}
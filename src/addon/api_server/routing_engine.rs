@@ -0,0 +1,173 @@
+use protobuf::Message;
+use std::error::Error;
+use std::fmt::Display;
+use std::sync::Arc;
+
+use crate::config::EngineConfig;
+use crate::protos::route_common::GeoPoint;
+use crate::protos::route_result::{RoadPoint, RouteResult};
+use crate::protos::route_server_param::RoutePlanServerParameter;
+
+use super::mock_engine::MockEngine;
+use super::road_graph::Location;
+use super::route_cache::fnv1a_128;
+#[cfg(feature = "native-engine")]
+use super::route_wrapper::NativeEngine;
+use super::walk_engine::WalkEngine;
+
+/// Routing library configuration directory of the native engine
+#[cfg(feature = "native-engine")]
+const ROUTING_LIB_PATH: &str = "routinglib";
+
+/// Speed of a walking route in meters per second
+pub const WALK_SPEED: f64 = 1.2;
+
+type RouteErrorCode = i32;
+
+/// `Engine`, `EmptyOutput` and `InvalidOutput` come from the native engine
+#[derive(Debug, PartialEq)]
+#[cfg_attr(not(feature = "native-engine"), allow(dead_code))]
+pub enum RouteError {
+  /// The routing library failed with the provided error code
+  Engine(RouteErrorCode),
+  /// The routing library succeeded without producing the named output
+  EmptyOutput(&'static str),
+  /// The engine produced a route which can't be decoded
+  InvalidOutput(String),
+  /// The route request can't be handed to the engine
+  InvalidParameter(String),
+  /// No route connects the requested points
+  NoRoute,
+  /// The engine can't produce the named output
+  Unsupported(&'static str),
+}
+
+impl Error for RouteError {}
+
+impl Display for RouteError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      RouteError::Engine(code) => write!(f, "{:?}", code),
+      RouteError::EmptyOutput(output) => write!(f, "no {} produced", output),
+      RouteError::InvalidOutput(reason) => write!(f, "invalid output: {}", reason),
+      RouteError::InvalidParameter(reason) => write!(f, "invalid parameter: {}", reason),
+      RouteError::NoRoute => write!(f, "no route between the requested points"),
+      RouteError::Unsupported(output) => write!(f, "{} not supported", output),
+    }
+  }
+}
+
+/// The start, middle and end points of `params`, a POI is entered at its
+/// `entry`
+pub fn waypoints(params: &RoutePlanServerParameter) -> Result<Vec<GeoPoint>, RouteError> {
+  let start = if params.has_startPoint() {
+    Some(params.get_startPoint())
+  } else if params.has_startPoi() {
+    params.get_startPoi().entry.as_ref()
+  } else {
+    None
+  }
+  .ok_or_else(|| RouteError::InvalidParameter(String::from("no start point")))?;
+  let end = if params.has_endPoint() {
+    Some(params.get_endPoint())
+  } else if params.has_endPoi() {
+    params.get_endPoi().entry.as_ref()
+  } else {
+    None
+  }
+  .ok_or_else(|| RouteError::InvalidParameter(String::from("no end point")))?;
+
+  let mut waypoints = vec![start.clone()];
+  waypoints.extend(params.middlePoint.iter().cloned());
+  waypoints.push(end.clone());
+  Ok(waypoints)
+}
+
+/// Route id of an engine planning identical routes for identical `params`
+pub fn route_id(params: &RoutePlanServerParameter) -> Result<u64, RouteError> {
+  let condition = params
+    .write_to_bytes()
+    .map_err(|error| RouteError::InvalidParameter(error.to_string()))?;
+  Ok(fnv1a_128(&condition) as u64)
+}
+
+pub fn road_point(location: Location) -> RoadPoint {
+  let mut point = RoadPoint::new();
+  point.longitude = location.0;
+  point.latitude = location.1;
+  point
+}
+
+/// Plans the routes of a `RoutePlanServerParameter`.
+///
+/// Engines block while planning, they are run on the `RoutePlanner`
+/// threads
+pub trait RoutingEngine: std::fmt::Debug + Send + Sync {
+  /// Name of the engine, logged on startup
+  fn name(&self) -> &'static str;
+
+  /// Fails when a point of `params` names a floor missing from its model.
+  /// Engines without indoor models accept every floor
+  fn check_floors(&self, _params: &RoutePlanServerParameter) -> Result<(), RouteError> {
+    Ok(())
+  }
+
+  /// The route id and the routes planned for `params`
+  fn find_path(
+    &self,
+    params: &RoutePlanServerParameter,
+  ) -> Result<(String, RouteResult), RouteError>;
+
+  /// The route id and the routes planned for `params` in the native binary
+  /// format of the engine
+  fn find_native_path(
+    &self,
+    _params: &RoutePlanServerParameter,
+  ) -> Result<(String, Vec<u8>), RouteError> {
+    Err(RouteError::Unsupported("native route format"))
+  }
+}
+
+/// Creates the engine named by `config.kind`, `native` runs the native
+/// routing library, `mock` plans without any routing data and `walk` walks
+/// the road graph at `config.graph`. Fails on unknown kinds, on `native` in a
+/// build without the `native-engine` feature and when the engine can't load
+/// its routing data
+pub fn make_engine(config: &EngineConfig) -> Result<Arc<dyn RoutingEngine>, String> {
+  let engine: Arc<dyn RoutingEngine> = match config.kind.as_str() {
+    "mock" => Arc::new(MockEngine::from_config(config)?),
+    "walk" => Arc::new(WalkEngine::from_config(config)?),
+    #[cfg(feature = "native-engine")]
+    "native" => Arc::new(NativeEngine::new(ROUTING_LIB_PATH)),
+    #[cfg(not(feature = "native-engine"))]
+    "native" => {
+      return Err(String::from(
+        "native engine is not built in, build with --features native-engine",
+      ))
+    }
+    kind => return Err(format!("unknown engine kind {}", kind)),
+  };
+  println!("make_engine->{}", engine.name());
+  Ok(engine)
+}
+
+#[cfg(test)]
+pub mod tests {
+  use super::*;
+
+  #[test]
+  fn rejects_unknown_kinds() {
+    let mut config = EngineConfig::default();
+    config.kind = String::from("mokc");
+    assert_eq!(
+      make_engine(&config).err(),
+      Some(String::from("unknown engine kind mokc"))
+    );
+
+    config.kind = String::from("native");
+    assert_eq!(
+      make_engine(&config).is_ok(),
+      cfg!(feature = "native-engine")
+    );
+  }
+}
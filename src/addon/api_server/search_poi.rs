@@ -25,13 +25,19 @@ use std::error::Error;
 use std::fmt::Display;
 use std::result::Result;
 use hyper::body::Bytes;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+
+use super::http_client::HttpClient;
+use super::poi_info::PoiDetailError;
+use crate::utils::trace::UpstreamTraceIds;
 
 
 
 const get_poi_detail: &str = "getPoiDetailByPoiId";
 
-#[derive(Deserialize, Debug)]
+// absent fields are empty, like the empty fields they are converted as
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(default)]
 pub struct PoiDetail  {
 	pub addressFloor :String, //`json:"addressFloor"`
 	pub buildFlag    :String, //`json:"buildFlag"`
@@ -49,85 +55,155 @@ pub struct PoiDetail  {
 	pub wroadXEntr   :String, //`json:"wroadXEntr"`
 	pub wroadYEntr   :String, //`json:"wroadYEntr"`
 }
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
 pub struct PoiDetailResponseBody {
-	pub data :PoiDetail, //`json:"data"`
+	pub data :Option<PoiDetail>, //`json:"data"`
 }
-#[derive(Deserialize, Debug)]
+// error envelopes come without body, rtnCode is a string or a number
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
 pub struct PoiDetailResponse  {
-	pub rtnCode :String, //`json:"rtnCode"`
+	pub rtnCode :Value, //`json:"rtnCode"`
+	pub rtnMsg  :String, //`json:"rtnMsg"`
 	pub traceId :String, //`json:"traceId"`
-	pub body :PoiDetailResponseBody, //`json:"body"`
+	pub body :Option<PoiDetailResponseBody>, //`json:"body"`
+}
+
+/// `rtnCode` of a POI id unknown to the POI service
+const RTN_CODE_NOT_FOUND: &str = "404";
+
+/// Whether `rtnCode` is a success, which is `200` or any code made of
+/// zeros such as `0` or `000000`
+fn rtn_code_succeeded(rtn_code: &str) -> bool {
+  "200" == rtn_code || (!rtn_code.is_empty() && rtn_code.chars().all(|c| '0' == c))
+}
+
+impl PoiDetailResponse {
+  fn rtn_code(&self) -> String {
+    match &self.rtnCode {
+      Value::String(rtn_code) => String::from(rtn_code.trim()),
+      Value::Number(rtn_code) => rtn_code.to_string(),
+      _ => String::new(),
+    }
+  }
+}
+
+/// The `PoiDetail` of a POI service response. The `traceId` of the
+/// envelope is logged and recorded in `upstream_trace_ids` whatever the
+/// outcome
+fn parse_poi_detail(
+  id: &str,
+  status: StatusCode,
+  bytes: &[u8],
+  upstream_trace_ids: &UpstreamTraceIds,
+) -> Result<PoiDetail, SearchError> {
+  let response = serde_json::from_slice::<PoiDetailResponse>(bytes);
+  if let Ok(response) = &response {
+    upstream_trace_ids.push(&response.traceId);
+    println!(
+      "search_poi_info[{}]->{} rtnCode={} traceId={}",
+      id, status, response.rtn_code(), response.traceId
+    );
+  } else {
+    println!("search_poi_info[{}]->{}", id, status);
+  }
+  if StatusCode::NOT_FOUND == status {
+    return Err(SearchError::NotFound(id.to_string()));
+  }
+  let response = match response {
+    Ok(response) if !response.rtn_code().is_empty() => response,
+    Ok(_) if StatusCode::OK == status => {
+      return Err(SearchError::Upstream(String::from("response without rtnCode")));
+    }
+    Err(error) if StatusCode::OK == status => {
+      return Err(SearchError::Upstream(error.to_string()));
+    }
+    _ => return Err(SearchError::Upstream(format!("unexpected status {}", status))),
+  };
+
+  let rtn_code = response.rtn_code();
+  if RTN_CODE_NOT_FOUND == rtn_code {
+    return Err(SearchError::NotFound(id.to_string()));
+  }
+  if !rtn_code_succeeded(&rtn_code) {
+    return Err(SearchError::Upstream(format!(
+      "rtnCode {} {} (traceId {})",
+      rtn_code, response.rtnMsg, response.traceId
+    )));
+  }
+  // a successful lookup without data has found nothing
+  match response.body.and_then(|body| body.data) {
+    Some(data) => Ok(data),
+    None => Err(SearchError::NotFound(id.to_string())),
+  }
 }
 
 #[derive(Debug)]
-pub struct SearchError {
-  pub code: i32,
+pub enum SearchError {
+  /// The POI service has no POI with the requested id
+  NotFound(String),
+  /// The POI service is unreachable or answered with an unexpected payload
+  Upstream(String),
+  /// The POI service answered with a POI which can't be routed to
+  InvalidDetail(PoiDetailError),
 }
 
 impl Display for SearchError {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-    write!(f, "SearchPoiInfo : error: {}", self.code)
+    match self {
+      SearchError::NotFound(id) => write!(f, "SearchPoiInfo : poi not found: {}", id),
+      SearchError::Upstream(reason) => write!(f, "SearchPoiInfo : upstream error: {}", reason),
+      SearchError::InvalidDetail(error) => write!(f, "SearchPoiInfo : invalid detail: {}", error),
+    }
   }
 }
 
 impl Error for SearchError {}
 
+/// Client of the POI service at `url`, `http://` or `https://`
 #[derive(Debug, Clone, Default)]
 pub struct SearchPoiInfo {
   pub url: String,
+  pub client: HttpClient,
+}
+
+/// Body of a `get_poi_detail` request
+fn poi_detail_body(id: &str) -> String {
+  json!({"data": {"poiId": id}}).to_string()
 }
 
 impl SearchPoiInfo {
-  pub async fn search_poi_info(&self, id: &String) -> Result<PoiDetail, SearchError> {
-    let client= Client::new();
-    let mut json = String::from("{\"data\": { \"poiId\":\"");
-    json += id;
-    json += "\"}}";
-    let req = Request::builder()
-      .method(Method::POST)
-      .uri(self.url.clone() + get_poi_detail)
-      .header("Content-Type", "application/json;charset=UTF-8")
-      .body(Body::from(json))
-      .expect("request poi info");
-
-    let result = client.request(req).await;
+  pub fn new(url: String, client: HttpClient) -> SearchPoiInfo {
+    SearchPoiInfo { url, client }
+  }
+
+  /// Looking a POI up has no side effect, failed lookups are retried. The
+  /// trace id given by the POI service is added to `upstream_trace_ids`
+  pub async fn search_poi_info(
+    &self,
+    id: &str,
+    upstream_trace_ids: &UpstreamTraceIds,
+  ) -> Result<PoiDetail, SearchError> {
+    let json = poi_detail_body(id);
+
+    let result = self.client.request(
+      Method::POST,
+      &(self.url.clone() + get_poi_detail),
+      "application/json;charset=UTF-8",
+      Bytes::from(json),
+      true,
+    ).await;
     match result {
-      Ok(mut response) => {
-        let status = response.status();
-        println!("search_poi_info->{}", status);
-        if StatusCode::OK != status {
-          println!("search_poi_info->{:?}", status);
-          return Err(SearchError { code: 1 });
-        }
-        let orb = response.body_mut().data().await;
-        match orb {
-          Some(result) =>{
-            match result {
-              Ok(bytes) =>{
-                let json = String::from_utf8(bytes.to_vec()).expect("json");
-                let json_object = serde_json::from_str::<serde_json::value::Value>(json.as_str()).expect("json_object");
-                let poiDetailResponse = serde_json::from_value::<PoiDetailResponse>(json_object).expect("msg");
-                let data = poiDetailResponse.body.data;
-                let poiName = &data.poiName;
-                println!("search_poi_info->{:?}", poiName);
-                return Ok(data);
-              }
-              Err(error)=>{
-                println!("search_poi_info->{:?}", error);
-        return Err(SearchError { code: 1 });
-              }
-            }
-          }, 
-          None =>{
-            println!("search_poi_info->None");
-          return Err(SearchError { code: 1 });
-          }
-        }
+      Ok((status, bytes)) => {
+        let data = parse_poi_detail(id, status, &bytes, upstream_trace_ids)?;
+        let poiName = &data.poiName;
+        println!("search_poi_info->{:?}", poiName);
+        return Ok(data);
       }
       Err(error) => {
         println!("search_poi_info->{:?}", error);
-        return Err(SearchError { code: 1 });
+        return Err(SearchError::Upstream(error.to_string()));
       }
     }
   }
@@ -135,35 +211,73 @@ impl SearchPoiInfo {
 
 #[cfg(test)]
 pub mod tests {
-  use tokio::task;
+  #[tokio::test]
+  async fn test_url_post() {
+    use super::*;
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::Server;
+    use std::convert::Infallible;
+
+    let make_service = make_service_fn(|_| async {
+      Ok::<_, Infallible>(service_fn(|request: Request<Body>| async move {
+        let path = request.uri().path().to_string();
+        let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+        let answer = if path == "/getPoiDetailByPoiId" && body == poi_detail_body("21") {
+          r#"{"rtnCode":"000000","traceId":"t-local","body":{"data":{"poiId":"21","poiName":"entrance"}}}"#
+        } else {
+          r#"{"rtnCode":404,"rtnMsg":"no poi","traceId":"t-missing"}"#
+        };
+        Ok::<_, Infallible>(Response::new(Body::from(answer)))
+      }))
+    });
+    let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+    let info = SearchPoiInfo::new(format!("http://{}/", server.local_addr()), HttpClient::default());
+    tokio::spawn(server);
+
+    let upstream_trace_ids = UpstreamTraceIds::default();
+    let ret = info.search_poi_info(&String::from("21"), &upstream_trace_ids).await.unwrap();
+    assert_eq!(ret.poiName, "entrance");
+
+    let ret = info.search_poi_info(&String::from("22"), &upstream_trace_ids).await;
+    assert!(matches!(ret, Err(SearchError::NotFound(_))));
+
+    assert_eq!(upstream_trace_ids.header_value(), Some(String::from("t-local, t-missing")));
+  }
   #[test]
-  fn test_url_post() {
+  fn honors_rtn_code() {
     use super::*;
-    let info = SearchPoiInfo {
-      url: String::from("http://httpbin.org"),
-    };
-    task::spawn_blocking(move || {
-      // let local_info = info.clone();
-      async move {
-        let ret =info.search_poi_info(&String::from("id")).await;  
-        match ret {
-          Ok(_) =>{
-            println!("ok");
-          },
-          Err(error) =>{
-            println!("{}", error);
-          },
-        }
-      }      
-    });    
+    let id = String::from("21");
+    let upstream_trace_ids = UpstreamTraceIds::default();
+
+    let ok = br#"{"rtnCode":"000000","traceId":"t1","body":{"data":{"poiId":"21","poiName":"entrance"}}}"#;
+    let ret = parse_poi_detail(&id, StatusCode::OK, ok, &upstream_trace_ids).unwrap();
+    assert_eq!(ret.poiName, "entrance");
+
+    let not_found = br#"{"rtnCode":404,"rtnMsg":"no poi","traceId":"t2"}"#;
+    let ret = parse_poi_detail(&id, StatusCode::OK, not_found, &upstream_trace_ids);
+    assert!(matches!(ret, Err(SearchError::NotFound(_))));
+
+    let empty = br#"{"rtnCode":"0","traceId":"t3","body":{"data":null}}"#;
+    let ret = parse_poi_detail(&id, StatusCode::OK, empty, &upstream_trace_ids);
+    assert!(matches!(ret, Err(SearchError::NotFound(_))));
+
+    let failed = br#"{"rtnCode":"500100","rtnMsg":"db down","traceId":"t4"}"#;
+    match parse_poi_detail(&id, StatusCode::INTERNAL_SERVER_ERROR, failed, &upstream_trace_ids) {
+      Err(SearchError::Upstream(reason)) => assert_eq!(reason, "rtnCode 500100 db down (traceId t4)"),
+      ret => panic!("unexpected {:?}", ret),
+    }
+
+    let ret = parse_poi_detail(&id, StatusCode::BAD_GATEWAY, b"<html>", &upstream_trace_ids);
+    assert!(matches!(ret, Err(SearchError::Upstream(_))));
+
+    assert_eq!(upstream_trace_ids.header_value(), Some(String::from("t1, t2, t3, t4")));
   }
   #[test]
   fn test_json() {
-    let id = "21";
-    let mut json = String::from("{\"data\": ");
-    json += "{ \"poiId\":\"";
-    json += id;
-    json += "\"}}";
+    use super::*;
+    let json = poi_detail_body("21");
     println!("test_json->{}", json);
+    assert_eq!(json, r#"{"data":{"poiId":"21"}}"#);
+    assert_eq!(poi_detail_body("2\"1"), r#"{"data":{"poiId":"2\"1"}}"#);
   }
 }
\ No newline at end of file
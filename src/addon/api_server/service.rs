@@ -1,119 +1,267 @@
+use super::endpoint;
+use super::error::ApiError;
+use super::guidance::Guidance;
+use super::http_client::HttpClient;
+use super::mock_engine::MockEngine;
+use super::poi_cache::{PoiCache, PoiCacheMetrics};
+use super::poi_info;
 use super::proto_wrapper;
 use super::redis_client::RedisClientOperation;
-use super::route_wrapper::RouteError;
-use super::search_poi::SearchPoiInfo;
-use super::{route_wrapper::RouteWrapper, search_poi::SearchError};
-use crate::config::Config;
-use crate::protos::route_common::GeoPoint;
-use crate::protos::route_server_param::PoiInfo;
-use protobuf::SingularPtrField;
+use super::route_cache::{self, CacheHealth, CachedRoutes};
+use super::route_format::RouteFormat;
+use super::route_planner::{PlannerMetrics, RoutePlanner};
+use super::router::Router;
+use super::routing_engine::{self, RoutingEngine};
+use super::search_poi::{SearchError, SearchPoiInfo};
+use crate::config::{ApiConfig, Config, DEFAULT_WALK_MODE};
+use crate::protos::route_server_param::{PoiInfo, RoutePlanServerParameter};
+use crate::utils::trace::UpstreamTraceIds;
+use protobuf::Message;
 use std::{result::Result, sync::Arc};
 
-#[derive(Debug, Default)]
+/// Routing library configuration holding the guidance phrases
+const GUIDANCE_CONFIG_PATH: &str = "routinglib/config/config.json";
+
+#[derive(Debug)]
 pub struct Service {
-  redis_client: RedisClientOperation,
+  route_cache: CachedRoutes,
+  poi_cache: PoiCache,
   poi_info: SearchPoiInfo,
+  planner: RoutePlanner,
+  engine: Arc<dyn RoutingEngine>,
+  /// Routes in this mode walk, the others drive
+  walk_mode: u32,
+  api_config: ApiConfig,
+  guidance: Arc<Guidance>,
+}
+
+impl Default for Service {
+  fn default() -> Self {
+    Self {
+      route_cache: Default::default(),
+      poi_cache: Default::default(),
+      poi_info: Default::default(),
+      planner: Default::default(),
+      engine: Arc::new(MockEngine::default()),
+      walk_mode: DEFAULT_WALK_MODE,
+      api_config: Default::default(),
+      guidance: Default::default(),
+    }
+  }
 }
 
 impl Clone for Service {
   fn clone(&self) -> Self {
     Self {
-      redis_client: self.redis_client.clone(),
+      route_cache: self.route_cache.clone(),
+      poi_cache: self.poi_cache.clone(),
       poi_info: self.poi_info.clone(),
+      planner: self.planner.clone(),
+      engine: Arc::clone(&self.engine),
+      walk_mode: self.walk_mode,
+      api_config: self.api_config.clone(),
+      guidance: self.guidance.clone(),
     }
   }
 }
 
 impl Service {
-  pub fn new(config: Arc<Config>) -> Service {
-    RouteWrapper::init(String::from("routinglib"));
-    Service {
-      redis_client: RedisClientOperation::new(&config.clone().redis_config),
-      poi_info: SearchPoiInfo {
-        url: config.poi_server(),
-      },
-    }
+  /// Fails when a cache backend is unknown or the routing engine can't be
+  /// created
+  pub fn new(config: Arc<Config>) -> Result<Service, String> {
+    let route_cache_config = config.route_cache();
+    let poi_cache_config = config.poi_cache();
+    // one connection pool shared by the caches on Redis
+    let redis_client =
+      if "memory" == route_cache_config.backend && "memory" == poi_cache_config.backend {
+        RedisClientOperation::default()
+      } else {
+        RedisClientOperation::new(&config.redis())
+      };
+    let poi_cache = route_cache::make_cache(
+      &poi_cache_config.backend,
+      &redis_client,
+      poi_cache_config.max_entries,
+      0,
+    )
+    .map_err(|error| format!("poiCache: {}", error))?;
+    Ok(Service {
+      route_cache: CachedRoutes::from_config(&redis_client, route_cache_config)?,
+      poi_cache: PoiCache::new(poi_cache, poi_cache_config),
+      poi_info: SearchPoiInfo::new(config.poi_server(), HttpClient::new(config.poi_client())),
+      planner: RoutePlanner::new(config.planner()),
+      engine: routing_engine::make_engine(&config.engine())?,
+      walk_mode: config.engine().walk_mode,
+      api_config: config.api(),
+      guidance: Arc::new(Guidance::load(GUIDANCE_CONFIG_PATH)),
+    })
   }
-  // pub fn save_value(&mut self, id: &String, value: &String) -> Result<(), RedisError> {
-  //   self.redis_client.set(id, value)
-  // }
-  pub fn get_value(&mut self, id: &String) -> Option<String> {
-    self.redis_client.get(id)
-  }
-  pub async fn get_poi_info(&self, id: &String) -> Result<PoiInfo, SearchError> {
-    let poi_info = self.poi_info.search_poi_info(id).await;
-    match poi_info {
-      Ok(info) => {
-        fn from_str_radix(value: &String) -> f64 {
-          let s = value.as_bytes();
-          let s_len = value.len();
-          let mut sss: [u8; 8] = [0; 8];
-          for i in 0..=s_len {
-            if 8 == i {
-              break;
-            }
-            sss[i] = s[i];
-          }
-          return f64::from_be_bytes(sss);
-        }
-        let mut ret = PoiInfo::new();
-        ret.poiID = u64::from_str_radix(info.poiId.as_str(), 10).expect("poiID");
-        ret.poiName = info.poiName;
-        ret.roadID = u64::from_str_radix(info.roadId.as_str(), 10).expect("roadID");
-        let mut point = GeoPoint::new();
-        point.latitude = from_str_radix(&info.latitude);
-        point.longitude = from_str_radix(&info.longitude);
-        point.height = 0;
-        point.modelID = u32::from_str_radix(info.modelId.as_str(), 10).expect("modelID");
-        point.floor = i32::from_str_radix(info.addressFloor.as_str(), 10).expect("floor");
-        ret.entry = SingularPtrField::from_option(Some(point));
-        ret.modelID = u64::from_str_radix(info.modelId.as_str(), 10).expect("modelID");
-        return Ok(ret);
-      }
-      Err(error) => {
+  pub fn max_body_size(&self) -> usize {
+    self.api_config.max_body_size
+  }
+  pub fn guidance(&self) -> &Guidance {
+    &self.guidance
+  }
+  /// Registers the API endpoints served by this `Service`
+  pub fn register_routes(router: &mut Router<Service>) {
+    router
+      .get("/api/v1/health", endpoint::health)
+      .get("/api/v1/metrics", endpoint::metrics)
+      .get("/api/v1/navi/:id", endpoint::get_id)
+      .get("/api/v1/navi:id", endpoint::get_id)
+      .post("/api/v1/navi", endpoint::get_path)
+      .post("/api/v1/navijson", endpoint::get_path_from_json);
+  }
+  /// The route cache backend and its state
+  pub async fn cache_health(&self) -> (&'static str, CacheHealth) {
+    (self.route_cache.backend(), self.route_cache.health().await)
+  }
+  pub fn poi_cache_metrics(&self) -> &PoiCacheMetrics {
+    self.poi_cache.metrics()
+  }
+  pub fn planner_metrics(&self) -> &PlannerMetrics {
+    self.planner.metrics()
+  }
+  pub async fn get_value(&self, id: &str) -> Result<Option<Vec<u8>>, ApiError> {
+    self
+      .route_cache
+      .get_route(id)
+      .await
+      .map_err(|error| ApiError::Cache(error.to_string()))
+  }
+  /// The `PoiInfo` routing to the POI `id` in the route `mode`
+  pub async fn get_poi_info(
+    &self,
+    id: &str,
+    mode: u32,
+    upstream_trace_ids: &UpstreamTraceIds,
+  ) -> Result<PoiInfo, SearchError> {
+    let detail = self
+      .poi_cache
+      .search(&self.poi_info, id, upstream_trace_ids)
+      .await
+      .map_err(|error| {
         println!("get_poi_info->{}", error);
-        return Err(SearchError { code: error.code });
-      }
-    }
+        error
+      })?;
+    poi_info::to_poi_info(&detail, self.walk_mode == mode).map_err(|error| {
+      println!("get_poi_info->{}", error);
+      SearchError::InvalidDetail(error)
+    })
   }
-  pub async fn find_path(&self, data: String) -> Result<(String, String), RouteError> {
-    let decode_data = proto_wrapper::client_to_server_protobuf(&data, &self).await;
-    match decode_data {
-      Ok(condition) =>{
-         match RouteWrapper::find_path(condition) {
-           Ok(path)=> {
-            return Ok(path);
-           }, 
-           Err(error) =>{
-              return Err(RouteError { code: error.code});
-           }
-         }
-      }
-      Err(error)=>{
-        println!("{}", error);
-        return Err(RouteError { code: 1});
+  pub async fn find_path(
+    &self,
+    data: &[u8],
+    format: RouteFormat,
+    locale: &str,
+    upstream_trace_ids: &UpstreamTraceIds,
+  ) -> Result<(String, Vec<u8>), ApiError> {
+    let condition =
+      proto_wrapper::client_to_server_protobuf(data, self, upstream_trace_ids).await?;
+    self.plan_route(condition, format, locale).await
+  }
+  pub async fn find_path_from_json(
+    &self,
+    data: String,
+    format: RouteFormat,
+    locale: &str,
+    upstream_trace_ids: &UpstreamTraceIds,
+  ) -> Result<(String, Vec<u8>), ApiError> {
+    let condition =
+      proto_wrapper::client_json_to_server_protobuf(&data, self, upstream_trace_ids).await?;
+    self.plan_route(condition, format, locale).await
+  }
+  /// Computes the route for an encoded `RoutePlanServerParameter`.
+  ///
+  /// The routing engine checks the floors of the requested points first.
+  /// Routes computed in protobuf are cached, an identical request is
+  /// served from the cache and skips the routing engine. Cache failures
+  /// are logged and never fail the request. The routing engine runs on the
+  /// `RoutePlanner` threads
+  async fn plan_route(
+    &self,
+    condition: Vec<u8>,
+    format: RouteFormat,
+    locale: &str,
+  ) -> Result<(String, Vec<u8>), ApiError> {
+    let params = RoutePlanServerParameter::parse_from_bytes(&condition)
+      .map_err(|error| ApiError::BadInput(error.to_string()))?;
+    self.engine.check_floors(&params)?;
+    let cacheable = RouteFormat::Native != format;
+    if cacheable {
+      match self.route_cache.lookup_plan(&condition).await {
+        Ok(Some((id, payload))) => {
+          return Ok((id, format.render(payload, &self.guidance, locale)?));
+        }
+        Ok(None) => (),
+        Err(error) => println!("plan_route->lookup:{}", error),
       }
     }
-    // RouteWrapper::find_path(decode_data.expect("msg"))
-  }
-  pub async fn find_path_from_json(&self, data: String) -> Result<(String, String), RouteError> {
-    let decode_data = proto_wrapper::client_json_to_server_protobuf(&data, &self).await;
-    match decode_data {
-      Ok(condition) =>{
-         match RouteWrapper::find_path(condition) {
-           Ok(path)=> {
-            return Ok(path);
-           }, 
-           Err(error) =>{
-              return Err(RouteError { code: error.code});
-           }
-         }
-      }
-      Err(error)=>{
-        println!("{}", error);
-        return Err(RouteError { code: 1});
+    let engine = Arc::clone(&self.engine);
+    let (id, payload) = if cacheable {
+      let (id, route_result) = self
+        .planner
+        .run(move || engine.find_path(&params))
+        .await??;
+      let payload = route_result
+        .write_to_bytes()
+        .map_err(|error| ApiError::InvalidRoute(error.to_string()))?;
+      (id, payload)
+    } else {
+      self
+        .planner
+        .run(move || engine.find_native_path(&params))
+        .await??
+    };
+    if cacheable {
+      if let Err(error) = self.route_cache.save_plan(&condition, &id, &payload).await {
+        println!("plan_route->save[{}]:{}", id, error);
       }
     }
-    // RouteWrapper::find_path(decode_data.expect("msg"))
+    Ok((id, format.render(payload, &self.guidance, locale)?))
+  }
+}
+
+#[cfg(test)]
+pub mod tests {
+  use super::*;
+  use crate::protos::route_common::GeoPoint;
+
+  fn indoor_point(longitude: f64, latitude: f64, floor: i32) -> GeoPoint {
+    let mut point = GeoPoint::new();
+    point.longitude = longitude;
+    point.latitude = latitude;
+    point.modelID = 12;
+    point.floor = floor;
+    point
+  }
+
+  #[tokio::test]
+  async fn checks_floors_before_planning() {
+    let mut config = Config::default();
+    config.route_cache_config.backend = String::from("memory");
+    config.engine_config.kind = String::from("walk");
+    config.engine_config.graph = String::from("fixtures/indoor_graph.geojson");
+    let services = Service::new(Arc::new(config)).unwrap();
+    let mut params = RoutePlanServerParameter::new();
+    params.set_startPoint(indoor_point(116.4402, 39.9099, 1));
+    params.set_endPoint(indoor_point(116.4413, 39.9108, 2));
+
+    let condition = params.write_to_bytes().unwrap();
+    assert!(services
+      .plan_route(condition, RouteFormat::Protobuf, "")
+      .await
+      .is_ok());
+
+    params.set_endPoint(indoor_point(116.4413, 39.9108, 3));
+    let condition = params.write_to_bytes().unwrap();
+    // the walk engine has no native format, the floors are checked before
+    let ret = services
+      .plan_route(condition, RouteFormat::Native, "")
+      .await;
+    match ret {
+      Err(ApiError::BadInput(reason)) => assert_eq!(reason, "endPoint floor 3 is not in model 12"),
+      ret => panic!("unexpected {:?}", ret.map(|(id, _)| id)),
+    }
   }
 }
@@ -0,0 +1,258 @@
+use std::error::Error;
+use std::fmt::Display;
+
+use crate::protos::{
+  route_client_param::RoutePlanClientParameter,
+  route_common::{GeoArea, GeoPoint},
+};
+
+/// A problem found on a single field of a request
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldError {
+  pub field: String,
+  pub message: String,
+}
+
+impl Display for FieldError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}: {}", self.field, self.message)
+  }
+}
+
+/// Every problem found on a request, validation keeps going after the
+/// first problem so clients get the complete list at once
+#[derive(Debug, Default)]
+pub struct ValidationErrors {
+  errors: Vec<FieldError>,
+}
+
+impl ValidationErrors {
+  pub fn new() -> Self {
+    ValidationErrors::default()
+  }
+
+  pub fn add(&mut self, field: &str, message: &str) {
+    self.errors.push(FieldError {
+      field: String::from(field),
+      message: String::from(message),
+    });
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.errors.is_empty()
+  }
+
+  pub fn errors(&self) -> &[FieldError] {
+    &self.errors
+  }
+
+  /// Turns the collected problems into a `Result`, succeeding only when
+  /// no problem was found
+  pub fn into_result(self) -> Result<(), ValidationErrors> {
+    if self.is_empty() {
+      Ok(())
+    } else {
+      Err(self)
+    }
+  }
+}
+
+impl Error for ValidationErrors {}
+
+impl Display for ValidationErrors {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let errors = self
+      .errors
+      .iter()
+      .map(|error| error.to_string())
+      .collect::<Vec<String>>();
+
+    write!(f, "{}", errors.join("; "))
+  }
+}
+
+fn validate_point(point: &GeoPoint, field: &str, errors: &mut ValidationErrors) {
+  if !point.longitude.is_finite() || point.longitude < -180.0 || point.longitude > 180.0 {
+    errors.add(
+      &format!("{}.longitude", field),
+      "must be between -180 and 180",
+    );
+  }
+
+  if !point.latitude.is_finite() || point.latitude < -90.0 || point.latitude > 90.0 {
+    errors.add(&format!("{}.latitude", field), "must be between -90 and 90");
+  }
+}
+
+fn validate_area(area: &GeoArea, field: &str, errors: &mut ValidationErrors) {
+  if area.minx < -180.0 || area.maxx > 180.0 || area.minx > area.maxx {
+    errors.add(
+      &format!("{}.minx", field),
+      "minx and maxx must be longitudes with minx not greater than maxx",
+    );
+  }
+
+  if area.miny < -90.0 || area.maxy > 90.0 || area.miny > area.maxy {
+    errors.add(
+      &format!("{}.miny", field),
+      "miny and maxy must be latitudes with miny not greater than maxy",
+    );
+  }
+}
+
+/// Validates a route endpoint which must be provided either as a POI id or
+/// as coordinates, but not both
+fn validate_endpoint(
+  point: Option<&GeoPoint>,
+  poi_id: &str,
+  name: &str,
+  errors: &mut ValidationErrors,
+) {
+  let point_field = format!("{}Point", name);
+  let poi_field = format!("{}PoiID", name);
+
+  match (point, poi_id.is_empty()) {
+    (Some(_), false) => errors.add(
+      &poi_field,
+      &format!("must not be combined with {}", point_field),
+    ),
+    (None, true) => errors.add(
+      name,
+      &format!("either {} or {} is required", point_field, poi_field),
+    ),
+    (Some(point), true) => validate_point(point, &point_field, errors),
+    (None, false) => {}
+  }
+}
+
+/// Validates the semantics of a `RoutePlanClientParameter`
+pub fn validate_client_params(params: &RoutePlanClientParameter) -> Result<(), ValidationErrors> {
+  let mut errors = ValidationErrors::new();
+
+  validate_endpoint(
+    params.startPoint.as_ref(),
+    &params.startPoiID,
+    "start",
+    &mut errors,
+  );
+  validate_endpoint(
+    params.endPoint.as_ref(),
+    &params.endPoiID,
+    "end",
+    &mut errors,
+  );
+
+  if !params.middlePoint.is_empty() && !params.middlePoiID.is_empty() {
+    errors.add("middlePoiID", "must not be combined with middlePoint");
+  }
+
+  for (index, point) in params.middlePoint.iter().enumerate() {
+    validate_point(point, &format!("middlePoint[{}]", index), &mut errors);
+  }
+
+  for (index, poi_id) in params.middlePoiID.iter().enumerate() {
+    if poi_id.is_empty() {
+      errors.add(&format!("middlePoiID[{}]", index), "must not be empty");
+    }
+  }
+
+  for (index, area) in params.avoidAreas.iter().enumerate() {
+    validate_area(area, &format!("avoidAreas[{}]", index), &mut errors);
+  }
+
+  if 0 != params.isYaw && params.routeIDInNavi.is_empty() {
+    errors.add("routeIDInNavi", "is required when isYaw is set");
+  }
+
+  errors.into_result()
+}
+
+#[cfg(test)]
+pub mod tests {
+  use protobuf::SingularPtrField;
+
+  use super::*;
+
+  fn make_point(longitude: f64, latitude: f64) -> GeoPoint {
+    let mut point = GeoPoint::new();
+    point.longitude = longitude;
+    point.latitude = latitude;
+    point
+  }
+
+  #[test]
+  fn accepts_points_and_poi_ids() {
+    let mut params = RoutePlanClientParameter::new();
+    params.startPoint = SingularPtrField::some(make_point(116.447209, 39.912554));
+    params.endPoiID = String::from("21");
+
+    assert!(validate_client_params(&params).is_ok());
+  }
+
+  #[test]
+  fn collects_every_problem() {
+    let mut params = RoutePlanClientParameter::new();
+    params.startPoint = SingularPtrField::some(make_point(190.0, -91.0));
+    params.middlePoint.push(make_point(0.0, f64::NAN));
+
+    let errors = validate_client_params(&params).unwrap_err();
+    let fields = errors
+      .errors()
+      .iter()
+      .map(|error| error.field.as_str())
+      .collect::<Vec<&str>>();
+
+    assert_eq!(
+      fields,
+      vec![
+        "startPoint.longitude",
+        "startPoint.latitude",
+        "end",
+        "middlePoint[0].latitude"
+      ]
+    );
+  }
+
+  #[test]
+  fn rejects_poi_id_mixed_with_coordinates() {
+    let mut params = RoutePlanClientParameter::new();
+    params.startPoint = SingularPtrField::some(make_point(116.447209, 39.912554));
+    params.startPoiID = String::from("21");
+    params.endPoiID = String::from("22");
+
+    let errors = validate_client_params(&params).unwrap_err();
+
+    assert_eq!(
+      errors.to_string(),
+      "startPoiID: must not be combined with startPoint"
+    );
+  }
+
+  #[test]
+  fn validates_waypoints_avoid_areas_and_yaw() {
+    let mut params = RoutePlanClientParameter::new();
+    params.startPoiID = String::from("21");
+    params.endPoiID = String::from("22");
+    params.middlePoint.push(make_point(116.447209, 39.912554));
+    params.middlePoiID.push(String::from("23"));
+    let mut area = GeoArea::new();
+    area.minx = 116.41;
+    area.maxx = 116.40;
+    area.miny = 39.90;
+    area.maxy = 39.91;
+    params.avoidAreas.push(area);
+    params.isYaw = 1;
+
+    let errors = validate_client_params(&params).unwrap_err();
+    let fields = errors
+      .errors()
+      .iter()
+      .map(|error| error.field.as_str())
+      .collect::<Vec<&str>>();
+
+    assert_eq!(
+      fields,
+      vec!["middlePoiID", "avoidAreas[0].minx", "routeIDInNavi"]
+    );
+  }
+}
@@ -0,0 +1,714 @@
+use protobuf::{RepeatedField, SingularPtrField};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use crate::config::{EngineConfig, DEFAULT_FEWEST_TURNS_POLICY};
+use crate::protos::route_common::GeoPoint;
+use crate::protos::route_result::{RoadDescribe, RoadForm, Route, RouteResult, WalkGuidePoint};
+use crate::protos::route_server_param::RoutePlanServerParameter;
+
+use super::road_graph::{bearing, distance, Location, Projection, Road, RoadGraph};
+use super::routing_engine::{self, RouteError, RoutingEngine, WALK_SPEED};
+
+/// Smallest change of heading in degrees counted as a turn
+const TURN_ANGLE: f64 = 30.0;
+
+/// Farthest a requested point may be from the road it starts or ends on,
+/// in meters
+const MAX_SNAP_DISTANCE: f64 = 500.0;
+
+/// Clockwise angle in degrees from heading `from` to heading `to`
+fn turn_angle(from: f64, to: f64) -> f64 {
+  (to - from + 360.0) % 360.0
+}
+
+fn is_turn(angle: f64) -> bool {
+  (TURN_ANGLE..=360.0 - TURN_ANGLE).contains(&angle)
+}
+
+fn location(point: &GeoPoint) -> Location {
+  (point.longitude, point.latitude)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Place {
+  Start,
+  Node(usize),
+  Goal,
+}
+
+/// Search state, `road` is the road arrived by when turns are counted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct State {
+  place: Place,
+  road: Option<usize>,
+}
+
+/// Cost of a route, fewer turns first and shorter second
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+struct Cost {
+  turns: u32,
+  length: f64,
+}
+
+/// Candidate of the search, ordered by lowest estimate first
+#[derive(Debug, PartialEq)]
+struct Candidate {
+  estimate: Cost,
+  cost: Cost,
+  state: State,
+}
+
+impl Eq for Candidate {}
+
+impl Ord for Candidate {
+  fn cmp(&self, other: &Self) -> Ordering {
+    other
+      .estimate
+      .partial_cmp(&self.estimate)
+      .unwrap_or(Ordering::Equal)
+  }
+}
+
+impl PartialOrd for Candidate {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+/// Part of a road walked from `from` to `to` meters along it, starting on
+/// `floor`
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Piece {
+  road: usize,
+  from: f64,
+  to: f64,
+  floor: i32,
+}
+
+impl Piece {
+  fn length(&self) -> f64 {
+    (self.to - self.from).abs()
+  }
+}
+
+/// `RoutingEngine` walking the roads of a `RoadGraph` with A*, for venues
+/// without the routing library data.
+///
+/// Each requested point is snapped to the closest road of its floor left
+/// by `avoidRoadIDs` and `avoidAreas`, a point with a `modelID` must be on
+/// a floor of the model. Floors are changed by the stairs, escalators and
+/// elevators of the graph. `fewest_turns_policy` plans the route with the
+/// fewest turns, every other `policy` plans the shortest route. Guide
+/// points are placed where the route turns or changes road form
+#[derive(Debug)]
+pub struct WalkEngine {
+  graph: RoadGraph,
+  /// `policy` planning the route with the fewest turns, the shortest of
+  /// them
+  fewest_turns_policy: u32,
+}
+
+impl Default for WalkEngine {
+  fn default() -> Self {
+    WalkEngine::new(RoadGraph::default())
+  }
+}
+
+impl WalkEngine {
+  pub fn new(graph: RoadGraph) -> WalkEngine {
+    WalkEngine {
+      graph,
+      fewest_turns_policy: DEFAULT_FEWEST_TURNS_POLICY,
+    }
+  }
+
+  /// Walks the roads of the GeoJSON or CSV file at `config.graph`. Fails
+  /// when the graph can't be loaded
+  pub fn from_config(config: &EngineConfig) -> Result<WalkEngine, String> {
+    match RoadGraph::load(&config.graph) {
+      Ok(graph) => {
+        println!(
+          "WalkEngine::from_config[{}]->{} roads",
+          config.graph,
+          graph.roads.len()
+        );
+        Ok(WalkEngine {
+          graph,
+          fewest_turns_policy: config.fewest_turns_policy,
+        })
+      }
+      Err(error) => Err(format!("walk engine graph {}: {}", config.graph, error)),
+    }
+  }
+
+  /// Whether each road may be walked by the route of `params`. Fails when
+  /// an `avoidRoadIDs` entry is not a road id
+  fn allowed_roads(&self, params: &RoutePlanServerParameter) -> Result<Vec<bool>, RouteError> {
+    let avoided = params
+      .avoidRoadIDs
+      .iter()
+      .map(|id| {
+        id.trim().parse::<u64>().map_err(|_| {
+          RouteError::InvalidParameter(format!("avoidRoadIDs {:?} is not a road id", id))
+        })
+      })
+      .collect::<Result<HashSet<u64>, RouteError>>()?;
+    Ok(
+      self
+        .graph
+        .roads
+        .iter()
+        .map(|road| {
+          !avoided.contains(&road.id) && !params.avoidAreas.iter().any(|area| road.crosses(area))
+        })
+        .collect(),
+    )
+  }
+
+  /// Fails when the requested `point` names a floor missing from its model
+  fn check_floor(&self, name: &str, point: &GeoPoint) -> Result<(), RouteError> {
+    if 0 == point.modelID || self.graph.has_floor(point.modelID, point.floor) {
+      return Ok(());
+    }
+    Err(RouteError::InvalidParameter(format!(
+      "{} floor {} is not in model {}",
+      name, point.floor, point.modelID
+    )))
+  }
+
+  /// Projects `point` on a road of its floor, of any model when the point
+  /// has no `modelID`
+  fn snap(&self, point: &GeoPoint, allowed: &[bool]) -> Result<Projection, RouteError> {
+    let on_floor = |index: usize| {
+      let road = &self.graph.roads[index];
+      !road.is_vertical()
+        && road.floor == point.floor
+        && (0 == point.modelID || road.model_id == point.modelID)
+    };
+    self
+      .graph
+      .project(location(point), |road| allowed[road] && on_floor(road))
+      .filter(|projection| projection.distance <= MAX_SNAP_DISTANCE)
+      .ok_or(RouteError::NoRoute)
+  }
+
+  /// Moves from `place`, each with the place it leads to
+  fn moves(
+    &self,
+    place: Place,
+    start: &Projection,
+    goal: &Projection,
+    allowed: &[bool],
+  ) -> Vec<(Place, Piece)> {
+    let roads = &self.graph.roads;
+    let mut moves = Vec::new();
+    match place {
+      Place::Start => {
+        let road = &roads[start.road];
+        let piece = |to| Piece {
+          road: start.road,
+          from: start.along,
+          to,
+          floor: road.floor,
+        };
+        moves.push((Place::Node(road.from), piece(0.0)));
+        moves.push((Place::Node(road.to), piece(road.length)));
+        if goal.road == start.road {
+          moves.push((Place::Goal, piece(goal.along)));
+        }
+      }
+      Place::Node(node) => {
+        for index in self.graph.roads_at(node) {
+          if !allowed[*index] {
+            continue;
+          }
+          let road = &roads[*index];
+          let (from, other) = if node == road.from {
+            (0.0, road.to)
+          } else {
+            (road.length, road.from)
+          };
+          let piece = |to| Piece {
+            road: *index,
+            from,
+            to,
+            floor: road.floor_at(node),
+          };
+          moves.push((Place::Node(other), piece(road.length - from)));
+          if goal.road == *index {
+            moves.push((Place::Goal, piece(goal.along)));
+          }
+        }
+      }
+      Place::Goal => (),
+    }
+    moves
+  }
+
+  /// Pieces of the best route from `from` to `to` for `policy`
+  fn plan_leg(
+    &self,
+    from: &GeoPoint,
+    to: &GeoPoint,
+    policy: u32,
+    allowed: &[bool],
+  ) -> Result<Vec<Piece>, RouteError> {
+    let (start, goal) = (self.snap(from, allowed)?, self.snap(to, allowed)?);
+    let count_turns = self.fewest_turns_policy == policy;
+    let roads = &self.graph.roads;
+    // straight to the goal, never longer than walking there
+    let heuristic = |place: Place| match place {
+      Place::Start => distance(start.location, goal.location),
+      Place::Node(node) => distance(self.graph.nodes[node], goal.location),
+      Place::Goal => 0.0,
+    };
+
+    let first = State {
+      place: Place::Start,
+      road: None,
+    };
+    let mut best = HashMap::new();
+    let mut parents: HashMap<State, (State, Piece)> = HashMap::new();
+    let mut candidates = BinaryHeap::new();
+    let zero = Cost {
+      turns: 0,
+      length: 0.0,
+    };
+    best.insert(first, zero);
+    candidates.push(Candidate {
+      estimate: zero,
+      cost: zero,
+      state: first,
+    });
+    while let Some(Candidate { cost, state, .. }) = candidates.pop() {
+      if Place::Goal == state.place {
+        let mut pieces = Vec::new();
+        let mut state = state;
+        while let Some((previous, piece)) = parents.get(&state) {
+          pieces.push(*piece);
+          state = *previous;
+        }
+        pieces.reverse();
+        return Ok(pieces);
+      }
+      if best.get(&state).map_or(false, |best| *best < cost) {
+        continue;
+      }
+      for (place, piece) in self.moves(state.place, &start, &goal, allowed) {
+        // starting or ending on a node walks no part of the road, which
+        // neither turns nor sets the heading
+        let walked = 0.0 < piece.length();
+        let turns = match (state.place, state.road) {
+          (Place::Node(node), Some(road)) if walked => {
+            let angle = turn_angle(
+              roads[road].bearing_into(node),
+              roads[piece.road].bearing_from(node),
+            );
+            is_turn(angle) as u32
+          }
+          _ => 0,
+        };
+        let next_cost = Cost {
+          turns: cost.turns + turns,
+          length: cost.length + piece.length(),
+        };
+        let next = State {
+          place,
+          road: match count_turns {
+            true if walked => Some(piece.road),
+            true => state.road,
+            false => None,
+          },
+        };
+        if best.get(&next).map_or(true, |best| next_cost < *best) {
+          best.insert(next, next_cost);
+          parents.insert(next, (state, piece));
+          candidates.push(Candidate {
+            estimate: Cost {
+              turns: next_cost.turns,
+              length: next_cost.length + heuristic(place),
+            },
+            cost: next_cost,
+            state: next,
+          });
+        }
+      }
+    }
+    Err(RouteError::NoRoute)
+  }
+
+  fn make_guide_point(
+    &self,
+    location: Location,
+    (in_road, in_bearing, in_length): (&Road, f64, f64),
+    (out_road, out_bearing, out_length): (&Road, f64, f64),
+  ) -> WalkGuidePoint {
+    let mut guide_point = WalkGuidePoint::new();
+    guide_point.guidePoint = SingularPtrField::some(routing_engine::road_point(location));
+    guide_point.inLinkForm = in_road.form;
+    guide_point.outLinkForm = out_road.form;
+    guide_point.inLinkClass = in_road.class;
+    guide_point.outLinkClass = out_road.class;
+    guide_point.outlinkID = out_road.id;
+    // no `guideType`, the guidance picks the direction from the angle
+    guide_point.angle = turn_angle(in_bearing, out_bearing).round() as u32 % 360;
+    guide_point.inLinkOrient = in_bearing.round() as u32 % 360;
+    guide_point.outLinkOrient = out_bearing.round() as u32 % 360;
+    guide_point.inLinkLen = in_length.round() as u32;
+    guide_point.outLinkLen = out_length.round() as u32;
+    guide_point.inLinkName = in_road.name.clone();
+    guide_point.outLinkName = out_road.name.clone();
+    guide_point
+  }
+
+  /// The route walking `pieces` in order
+  fn make_route(&self, pieces: &[Piece]) -> Route {
+    let roads = &self.graph.roads;
+    let mut points: Vec<Location> = Vec::new();
+    let mut forms: Vec<RoadForm> = Vec::new();
+    let mut guide_points = Vec::new();
+    let mut previous: Option<(&Road, f64)> = None;
+    let mut since_guide_point = 0.0;
+    for piece in pieces {
+      let road = &roads[piece.road];
+      let mut slice = road.slice(piece.from, piece.to);
+      slice.dedup();
+      if road.is_vertical() && 1 == slice.len() {
+        slice.push(slice[0]);
+      }
+      if slice.len() < 2 {
+        continue;
+      }
+      // an elevator keeps the heading it is entered with
+      let out_bearing = match previous {
+        Some((_, in_bearing)) if slice[0] == slice[1] => in_bearing,
+        _ => bearing(slice[0], slice[1]),
+      };
+      if let Some((in_road, in_bearing)) = previous {
+        if is_turn(turn_angle(in_bearing, out_bearing)) || in_road.form != road.form {
+          guide_points.push(self.make_guide_point(
+            slice[0],
+            (in_road, in_bearing, since_guide_point),
+            (road, out_bearing, piece.length()),
+          ));
+          since_guide_point = 0.0;
+        }
+      }
+
+      let last = slice.len() - 1;
+      let heading = match previous {
+        Some((_, in_bearing)) if slice[0] == slice[last] => in_bearing,
+        _ => bearing(slice[last - 1], slice[last]),
+      };
+      let skip = (points.last() == Some(&slice[0])) as usize;
+      let from_index = (points.len() - skip) as u32;
+      points.extend(slice.into_iter().skip(skip));
+      let to_index = (points.len() - 1) as u32;
+      match forms.last_mut() {
+        Some(form)
+          if form.form == road.form
+            && form.modelID == road.model_id
+            && form.floor == piece.floor
+            && form.toIndex == from_index =>
+        {
+          form.toIndex = to_index;
+        }
+        _ => {
+          let mut form = RoadForm::new();
+          form.fromIndex = from_index;
+          form.toIndex = to_index;
+          form.form = road.form;
+          form.modelID = road.model_id;
+          form.floor = piece.floor;
+          forms.push(form);
+        }
+      }
+      since_guide_point += piece.length();
+      previous = Some((road, heading));
+    }
+
+    let mut route = Route::new();
+    route.roadPoints = points.into_iter().map(routing_engine::road_point).collect();
+    route.roadForms = RepeatedField::from_vec(forms);
+    route.guidePoints = RepeatedField::from_vec(guide_points);
+    route
+  }
+}
+
+impl RoutingEngine for WalkEngine {
+  fn name(&self) -> &'static str {
+    "walk"
+  }
+
+  fn check_floors(&self, params: &RoutePlanServerParameter) -> Result<(), RouteError> {
+    let waypoints = routing_engine::waypoints(params)?;
+    self.check_floor("startPoint", &waypoints[0])?;
+    for point in &waypoints[1..waypoints.len() - 1] {
+      self.check_floor("middlePoint", point)?;
+    }
+    self.check_floor("endPoint", &waypoints[waypoints.len() - 1])
+  }
+
+  fn find_path(
+    &self,
+    params: &RoutePlanServerParameter,
+  ) -> Result<(String, RouteResult), RouteError> {
+    let waypoints = routing_engine::waypoints(params)?;
+    self.check_floors(params)?;
+
+    let allowed = self.allowed_roads(params)?;
+    let mut pieces = Vec::new();
+    for leg in waypoints.windows(2) {
+      pieces.extend(self.plan_leg(&leg[0], &leg[1], params.policy, &allowed)?);
+    }
+    let length = pieces.iter().map(Piece::length).sum::<f64>();
+    let route_id = routing_engine::route_id(params)?;
+
+    let mut route = self.make_route(&pieces);
+    let mut describe = RoadDescribe::new();
+    describe.routeID = route_id;
+    describe.mode = params.mode;
+    describe.policy = params.policy;
+    describe.length = length.round() as u32;
+    describe.time = (length / WALK_SPEED).round() as u32;
+    describe.turningCnt = route
+      .guidePoints
+      .iter()
+      .filter(|guide_point| is_turn(guide_point.angle as f64))
+      .count() as u32;
+    describe.routeStartPoint = SingularPtrField::some(waypoints[0].clone());
+    describe.routeEndPoint = SingularPtrField::some(waypoints[waypoints.len() - 1].clone());
+    route.describe = SingularPtrField::some(describe);
+
+    let mut result = RouteResult::new();
+    result.version = params.version;
+    result.routes = RepeatedField::from_vec(vec![route]);
+    Ok((route_id.to_string(), result))
+  }
+}
+
+#[cfg(test)]
+pub mod tests {
+  use super::*;
+  use crate::addon::api_server::floor_legs::{self, FORM_ELEVATOR, FORM_ESCALATOR};
+  use crate::protos::route_common::GeoArea;
+
+  /// `policy` planning the shortest route
+  const POLICY_SHORTEST: u32 = 0;
+
+  fn make_point(longitude: f64, latitude: f64) -> GeoPoint {
+    let mut point = GeoPoint::new();
+    point.longitude = longitude;
+    point.latitude = latitude;
+    point
+  }
+
+  fn make_params(policy: u32) -> RoutePlanServerParameter {
+    let mut params = RoutePlanServerParameter::new();
+    params.mode = 1;
+    params.policy = policy;
+    params.set_startPoint(make_point(116.4469, 39.9119));
+    params.set_endPoint(make_point(116.4501, 39.9119));
+    params
+  }
+
+  fn make_engine() -> WalkEngine {
+    let graph = RoadGraph::from_geojson(include_str!("../../../fixtures/walk_graph.geojson"));
+    WalkEngine::new(graph.unwrap())
+  }
+
+  fn road_names(result: &RouteResult) -> Vec<String> {
+    let mut names = vec![];
+    for guide_point in result.routes[0].guidePoints.iter() {
+      if names.is_empty() {
+        names.push(guide_point.inLinkName.clone());
+      }
+      names.push(guide_point.outLinkName.clone());
+    }
+    names
+  }
+
+  #[test]
+  fn walks_the_shortest_route() {
+    let engine = make_engine();
+    let (id, result) = engine.find_path(&make_params(POLICY_SHORTEST)).unwrap();
+    let route = &result.routes[0];
+    let describe = route.get_describe();
+
+    assert_eq!(describe.routeID.to_string(), id);
+    assert_eq!(describe.length, 367);
+    assert_eq!(describe.time, 306);
+    assert_eq!(describe.turningCnt, 4);
+    assert_eq!(route.roadPoints.len(), 6);
+    assert_eq!(route.roadPoints[0].longitude, 116.447);
+    assert_eq!(route.roadPoints[5].longitude, 116.45);
+
+    let forms = route
+      .roadForms
+      .iter()
+      .map(|form| (form.fromIndex, form.toIndex, form.form))
+      .collect::<Vec<_>>();
+    assert_eq!(forms, vec![(0, 2, 0), (2, 3, 1), (3, 5, 0)]);
+
+    let turn = &route.guidePoints[0];
+    assert_eq!(turn.angle, 270);
+    assert_eq!(turn.inLinkLen, 85);
+    assert_eq!(turn.outlinkID, 21);
+    assert_eq!(turn.outLinkForm, 0);
+    assert_eq!(route.guidePoints[1].outLinkForm, 1);
+    assert_eq!(
+      road_names(&result),
+      vec!["West Lane", "Detour", "Footbridge", "Detour", "East Lane"]
+    );
+  }
+
+  #[test]
+  fn walks_the_route_with_fewest_turns() {
+    let engine = make_engine();
+    let (_, result) = engine
+      .find_path(&make_params(DEFAULT_FEWEST_TURNS_POLICY))
+      .unwrap();
+    let describe = result.routes[0].get_describe();
+
+    assert_eq!(describe.turningCnt, 2);
+    assert_eq!(describe.length, 701);
+    assert_eq!(
+      road_names(&result),
+      vec!["North Path", "North Street", "East Path"]
+    );
+  }
+
+  #[test]
+  fn avoids_roads_and_areas() {
+    let engine = make_engine();
+    let mut params = make_params(POLICY_SHORTEST);
+    params.avoidRoadIDs = RepeatedField::from_vec(vec![String::from("22")]);
+    let (_, result) = engine.find_path(&params).unwrap();
+    assert_eq!(result.routes[0].get_describe().turningCnt, 2);
+
+    let mut area = GeoArea::new();
+    area.minx = 116.4484;
+    area.maxx = 116.4486;
+    area.miny = 39.9124;
+    area.maxy = 39.9126;
+    let mut params = make_params(POLICY_SHORTEST);
+    params.avoidAreas = RepeatedField::from_vec(vec![area]);
+    let (_, result) = engine.find_path(&params).unwrap();
+    assert_eq!(
+      road_names(&result),
+      vec!["North Path", "North Street", "East Path"]
+    );
+
+    // the middle point is too far from the roads left
+    let mut params = make_params(POLICY_SHORTEST);
+    params.avoidRoadIDs = RepeatedField::from_vec(vec![String::from("5")]);
+    params.middlePoint = RepeatedField::from_vec(vec![make_point(116.448, 39.92)]);
+    assert_eq!(
+      engine.find_path(&params).map(|_| ()),
+      Err(RouteError::NoRoute)
+    );
+
+    let mut params = make_params(POLICY_SHORTEST);
+    params.avoidRoadIDs = RepeatedField::from_vec(vec![String::from("22"), String::from("road-5")]);
+    assert_eq!(
+      engine.find_path(&params).map(|_| ()),
+      Err(RouteError::InvalidParameter(String::from(
+        "avoidRoadIDs \"road-5\" is not a road id"
+      )))
+    );
+  }
+
+  #[test]
+  fn plans_fewest_turns_for_the_configured_policy() {
+    let mut engine = make_engine();
+    engine.fewest_turns_policy = 7;
+    let (_, result) = engine.find_path(&make_params(7)).unwrap();
+    let (_, fewest_turns) = make_engine()
+      .find_path(&make_params(DEFAULT_FEWEST_TURNS_POLICY))
+      .unwrap();
+
+    assert_eq!(road_names(&result), road_names(&fewest_turns));
+    // the default policy plans the shortest route on this engine
+    let (_, shortest) = engine
+      .find_path(&make_params(DEFAULT_FEWEST_TURNS_POLICY))
+      .unwrap();
+    assert_ne!(road_names(&shortest), road_names(&fewest_turns));
+  }
+
+  #[test]
+  fn walks_along_a_single_road() {
+    let engine = make_engine();
+    let mut params = make_params(POLICY_SHORTEST);
+    params.set_startPoint(make_point(116.4475, 39.9141));
+    params.set_endPoint(make_point(116.4495, 39.9141));
+    let (_, result) = engine.find_path(&params).unwrap();
+    let route = &result.routes[0];
+
+    assert_eq!(route.roadPoints.len(), 3);
+    assert_eq!(route.roadPoints[1].longitude, 116.4485);
+    assert_eq!(route.guidePoints.len(), 0);
+    assert_eq!(route.get_describe().length, 171);
+  }
+
+  #[test]
+  fn walks_between_floors() {
+    let graph = RoadGraph::from_geojson(include_str!("../../../fixtures/indoor_graph.geojson"));
+    let engine = WalkEngine::new(graph.unwrap());
+    let indoor_point = |longitude, latitude, floor| {
+      let mut point = make_point(longitude, latitude);
+      point.modelID = 12;
+      point.floor = floor;
+      point
+    };
+    let mut params = make_params(POLICY_SHORTEST);
+    params.set_startPoint(indoor_point(116.4402, 39.9099, 1));
+    params.set_endPoint(indoor_point(116.4413, 39.9108, 2));
+    let legs = |params: &RoutePlanServerParameter| {
+      let (_, result) = engine.find_path(params).unwrap();
+      floor_legs::floor_legs(&result.routes[0])
+        .iter()
+        .map(|leg| {
+          let facility = leg
+            .transition
+            .as_ref()
+            .map(|transition| transition.facility);
+          (leg.floor, leg.from_index, leg.to_index, facility)
+        })
+        .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+      legs(&params),
+      vec![(1, 0, 2, Some(FORM_ESCALATOR)), (2, 2, 3, None)]
+    );
+    params.avoidRoadIDs = RepeatedField::from_vec(vec![String::from("102")]);
+    assert_eq!(
+      legs(&params),
+      vec![(1, 0, 2, Some(FORM_ELEVATOR)), (2, 2, 5, None)]
+    );
+    let (_, result) = engine.find_path(&params).unwrap();
+    let elevator = &result.routes[0].guidePoints[0];
+    assert_eq!((elevator.outLinkForm, elevator.angle), (FORM_ELEVATOR, 0));
+    assert_eq!(result.routes[0].guidePoints[1].angle, 90);
+
+    params.set_endPoint(indoor_point(116.4413, 39.9108, 3));
+    assert_eq!(
+      engine.find_path(&params).map(|_| ()),
+      Err(RouteError::InvalidParameter(String::from(
+        "endPoint floor 3 is not in model 12"
+      )))
+    );
+  }
+
+  #[test]
+  fn reports_unreadable_graphs() {
+    let mut config = EngineConfig::default();
+    config.kind = String::from("walk");
+    config.graph = String::from("fixtures/missing_graph.geojson");
+
+    let error = WalkEngine::from_config(&config).unwrap_err();
+    assert!(error.starts_with("walk engine graph fixtures/missing_graph.geojson: "));
+    assert!(routing_engine::make_engine(&config).is_err());
+  }
+}
@@ -51,11 +51,23 @@ impl From<i32> for ServerType {
 // 	ReadTimeout  time.Duration `yaml:"readTimeout"`
 // }
 
+/// Default delay in milliseconds before dialing Redis again after a
+/// failed dial
+pub const DEFAULT_REDIS_MIN_RETRY_BACKOFF: i64 = 100;
+
+/// Default upper bound in milliseconds of the delay between dials
+pub const DEFAULT_REDIS_MAX_RETRY_BACKOFF: i64 = 30 * 1000;
+
 #[derive(Debug, Clone)]
 pub struct RedisConnect {
     pub dial_timeout: i64,
     pub write_timeout: i64,
     pub read_timeout: i64,
+    /// Delay in milliseconds after a failed dial, doubled on every
+    /// consecutive failure
+    pub min_retry_backoff: i64,
+    /// Maximum delay in milliseconds between dials
+    pub max_retry_backoff: i64,
 }
 
 impl RedisConnect {}
@@ -66,6 +78,32 @@ impl Default for RedisConnect {
             dial_timeout: Default::default(),
             write_timeout: Default::default(),
             read_timeout: Default::default(),
+            min_retry_backoff: DEFAULT_REDIS_MIN_RETRY_BACKOFF,
+            max_retry_backoff: DEFAULT_REDIS_MAX_RETRY_BACKOFF,
+        }
+    }
+}
+
+/// Default maximum number of open connections to Redis
+pub const DEFAULT_REDIS_POOL_SIZE: usize = 64;
+
+#[derive(Debug, Clone)]
+pub struct RedisPool {
+    /// Maximum number of connections open at once
+    pub pool_size: usize,
+    /// Number of idle connections kept open and ready to use
+    pub min_idle_conns: usize,
+    /// Age in seconds after which a connection is closed, `0` keeps
+    /// connections open until they fail
+    pub max_conn_age: u64,
+}
+
+impl Default for RedisPool {
+    fn default() -> Self {
+        Self {
+            pool_size: DEFAULT_REDIS_POOL_SIZE,
+            min_idle_conns: Default::default(),
+            max_conn_age: Default::default(),
         }
     }
 }
@@ -73,10 +111,23 @@ impl Default for RedisConnect {
 #[derive(Debug, Clone)]
 pub struct RedisConfig {
     pub pass: String,
+    /// ACL user name, empty for the `default` user
+    pub username: String,
+    /// Database index, only used in single mode as clusters only have `0`
+    pub db: i64,
+    /// Connects over TLS, like a `rediss://` host does
+    pub tls: bool,
+    /// Accepts any certificate from the server when connecting over TLS
+    pub tls_insecure: bool,
+    /// `single`, `cluster` or `sentinel`
     pub mode: String,
+    /// Name of the master group monitored by the sentinels
+    pub master_name: String,
+    /// Password of the sentinels, which may differ from the nodes one
+    pub sentinel_password: String,
     pub hosts: String,
     pub connect: RedisConnect,
-    pub pool: String,
+    pub pool: RedisPool,
 }
 
 impl RedisConfig {}
@@ -85,7 +136,13 @@ impl Default for RedisConfig {
     fn default() -> Self {
         Self {
             pass: Default::default(),
+            username: Default::default(),
+            db: Default::default(),
+            tls: Default::default(),
+            tls_insecure: Default::default(),
             mode: Default::default(),
+            master_name: Default::default(),
+            sentinel_password: Default::default(),
             hosts: Default::default(),
             connect: Default::default(),
             pool: Default::default(),
@@ -93,6 +150,244 @@ impl Default for RedisConfig {
     }
 }
 
+/// Default limit in bytes for the body of an API request
+pub const DEFAULT_MAX_BODY_SIZE: usize = 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct ApiConfig {
+    /// Maximum size in bytes of a request body, bigger requests are
+    /// rejected with `413 Payload Too Large`
+    pub max_body_size: usize,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+        }
+    }
+}
+
+/// Default time to live in seconds of a computed route cached by its id
+pub const DEFAULT_ROUTE_TTL: u64 = 24 * 60 * 60;
+
+/// Default time to live in seconds of the route planned for a request
+pub const DEFAULT_PLAN_TTL: u64 = 10 * 60;
+
+/// Default maximum number of entries of the in-memory route cache
+pub const DEFAULT_ROUTE_CACHE_MAX_ENTRIES: usize = 10000;
+
+/// Default maximum size in bytes of the in-memory route cache
+pub const DEFAULT_ROUTE_CACHE_MAX_BYTES: usize = 256 * 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct RouteCacheConfig {
+    /// `redis`, or `memory` to keep routes in the server process
+    pub backend: String,
+    /// Maximum number of entries of the `memory` backend, `0` is unlimited
+    pub max_entries: usize,
+    /// Maximum size in bytes of the `memory` backend, `0` is unlimited
+    pub max_bytes: usize,
+    /// Seconds a computed route is served by its id, `0` never expires
+    pub route_ttl: u64,
+    /// Seconds identical route requests reuse a computed route, `0`
+    /// disables the lookup of routes by request
+    pub plan_ttl: u64,
+}
+
+impl Default for RouteCacheConfig {
+    fn default() -> Self {
+        Self {
+            backend: String::from("redis"),
+            max_entries: DEFAULT_ROUTE_CACHE_MAX_ENTRIES,
+            max_bytes: DEFAULT_ROUTE_CACHE_MAX_BYTES,
+            route_ttl: DEFAULT_ROUTE_TTL,
+            plan_ttl: DEFAULT_PLAN_TTL,
+        }
+    }
+}
+
+/// Default time to live in seconds of a POI detail
+pub const DEFAULT_POI_TTL: u64 = 60 * 60;
+
+/// Default time to live in seconds of an unknown POI id
+pub const DEFAULT_POI_NEGATIVE_TTL: u64 = 60;
+
+/// Default maximum number of entries of the in-memory POI cache
+pub const DEFAULT_POI_CACHE_MAX_ENTRIES: usize = 10000;
+
+#[derive(Debug, Clone)]
+pub struct PoiCacheConfig {
+    /// `memory`, or `redis` to share POI details between servers
+    pub backend: String,
+    /// Maximum number of entries of the `memory` backend, `0` is unlimited
+    pub max_entries: usize,
+    /// Seconds a POI detail is reused, `0` disables the cache
+    pub ttl: u64,
+    /// Seconds a POI id unknown to the POI service is answered as not
+    /// found without asking again, `0` disables negative caching
+    pub negative_ttl: u64,
+}
+
+impl Default for PoiCacheConfig {
+    fn default() -> Self {
+        Self {
+            backend: String::from("memory"),
+            max_entries: DEFAULT_POI_CACHE_MAX_ENTRIES,
+            ttl: DEFAULT_POI_TTL,
+            negative_ttl: DEFAULT_POI_NEGATIVE_TTL,
+        }
+    }
+}
+
+/// Default time in milliseconds to connect to the POI service
+pub const DEFAULT_POI_CONNECT_TIMEOUT: u64 = 1000;
+
+/// Default time in milliseconds for a POI service request, reading the
+/// response included
+pub const DEFAULT_POI_REQUEST_TIMEOUT: u64 = 3000;
+
+/// Default seconds an idle connection to the POI service is kept open
+pub const DEFAULT_POI_POOL_IDLE_TIMEOUT: u64 = 90;
+
+/// Default number of idle connections kept open per POI service host
+pub const DEFAULT_POI_POOL_MAX_IDLE_PER_HOST: usize = 32;
+
+/// Default number of retries of a failed idempotent POI service request
+pub const DEFAULT_POI_RETRIES: u32 = 2;
+
+/// Default delay in milliseconds before the first retry
+pub const DEFAULT_POI_RETRY_BACKOFF: u64 = 100;
+
+/// Default upper bound in milliseconds of the delay between retries
+pub const DEFAULT_POI_MAX_RETRY_BACKOFF: u64 = 2000;
+
+/// Default number of consecutive failures opening the circuit of a host
+pub const DEFAULT_POI_BREAKER_THRESHOLD: u32 = 5;
+
+/// Default time in milliseconds requests to an open circuit fail fast
+pub const DEFAULT_POI_BREAKER_COOLDOWN: u64 = 30 * 1000;
+
+#[derive(Debug, Clone)]
+pub struct PoiClientConfig {
+    /// Milliseconds to establish a connection, `0` waits indefinitely
+    pub connect_timeout: u64,
+    /// Milliseconds for a whole attempt, `0` waits indefinitely
+    pub request_timeout: u64,
+    /// Seconds an idle connection is kept open for reuse
+    pub pool_idle_timeout: u64,
+    /// Maximum number of idle connections kept open per host
+    pub pool_max_idle_per_host: usize,
+    /// Retries of an idempotent request failing or answered with a server
+    /// error
+    pub retries: u32,
+    /// Milliseconds before the first retry, doubled on every retry and
+    /// randomized
+    pub retry_backoff: u64,
+    /// Maximum delay in milliseconds between retries
+    pub max_retry_backoff: u64,
+    /// Consecutive failures after which requests to a host fail fast, `0`
+    /// disables the circuit breaker
+    pub breaker_threshold: u32,
+    /// Milliseconds requests to a host fail fast before one is let through
+    /// to probe it
+    pub breaker_cooldown: u64,
+}
+
+impl Default for PoiClientConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: DEFAULT_POI_CONNECT_TIMEOUT,
+            request_timeout: DEFAULT_POI_REQUEST_TIMEOUT,
+            pool_idle_timeout: DEFAULT_POI_POOL_IDLE_TIMEOUT,
+            pool_max_idle_per_host: DEFAULT_POI_POOL_MAX_IDLE_PER_HOST,
+            retries: DEFAULT_POI_RETRIES,
+            retry_backoff: DEFAULT_POI_RETRY_BACKOFF,
+            max_retry_backoff: DEFAULT_POI_MAX_RETRY_BACKOFF,
+            breaker_threshold: DEFAULT_POI_BREAKER_THRESHOLD,
+            breaker_cooldown: DEFAULT_POI_BREAKER_COOLDOWN,
+        }
+    }
+}
+
+/// Default number of threads computing routes
+pub const DEFAULT_PLANNER_WORKERS: usize = 4;
+
+/// Default number of route requests waiting for a free thread
+pub const DEFAULT_PLANNER_QUEUE_DEPTH: usize = 64;
+
+/// Default time in milliseconds a route request waits for its route
+pub const DEFAULT_PLANNER_TIMEOUT: u64 = 10 * 1000;
+
+/// Default seconds a client is told to wait when the queue is full
+pub const DEFAULT_PLANNER_RETRY_AFTER: u64 = 1;
+
+#[derive(Debug, Clone)]
+pub struct PlannerConfig {
+    /// Threads dedicated to the routing library
+    pub workers: usize,
+    /// Route requests waiting for a thread, further requests are rejected
+    /// with `503 Service Unavailable`
+    pub queue_depth: usize,
+    /// Milliseconds a route request waits, queued and computing, `0` waits
+    /// indefinitely
+    pub timeout: u64,
+    /// Seconds sent in the `Retry-After` header of rejected requests
+    pub retry_after: u64,
+}
+
+impl Default for PlannerConfig {
+    fn default() -> Self {
+        Self {
+            workers: DEFAULT_PLANNER_WORKERS,
+            queue_depth: DEFAULT_PLANNER_QUEUE_DEPTH,
+            timeout: DEFAULT_PLANNER_TIMEOUT,
+            retry_after: DEFAULT_PLANNER_RETRY_AFTER,
+        }
+    }
+}
+
+/// Default routing engine, the native routing library. It needs a build with
+/// the `native-engine` feature, the `mock` engine is meant for tests and
+/// development
+pub const DEFAULT_ENGINE_KIND: &str = "native";
+
+/// Default `mode` of a walking route. The protos leave the values of `mode`
+/// undocumented, `engine.walkMode` sets the one the routing library walks
+/// with
+pub const DEFAULT_WALK_MODE: u32 = 1;
+
+/// Default `policy` of the `walk` engine planning the route with the fewest
+/// turns, set by `engine.fewestTurnsPolicy`
+pub const DEFAULT_FEWEST_TURNS_POLICY: u32 = 1;
+
+#[derive(Debug, Clone)]
+pub struct EngineConfig {
+    /// `native` runs the routing library, `mock` plans without routing data
+    /// and `walk` walks the roads of `graph`
+    pub kind: String,
+    /// Road graph of the `walk` engine, a GeoJSON or `.csv` file, or the
+    /// fixture graph the `mock` engine routes on, straight lines when empty
+    pub graph: String,
+    /// `RoutePlanServerParameter.mode` of a walking route, every other mode
+    /// drives
+    pub walk_mode: u32,
+    /// `policy` the `walk` engine plans the route with the fewest turns
+    /// for, every other policy plans the shortest route
+    pub fewest_turns_policy: u32,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            kind: String::from(DEFAULT_ENGINE_KIND),
+            graph: String::new(),
+            walk_mode: DEFAULT_WALK_MODE,
+            fewest_turns_policy: DEFAULT_FEWEST_TURNS_POLICY,
+        }
+    }
+}
+
 /// Server instance configuration used on initialization
 #[derive(Debug)]
 pub struct Config {
@@ -108,6 +403,12 @@ pub struct Config {
     pub action: ServerType,
     pub redis_config: RedisConfig,
     pub poi_server: String,
+    pub api_config: ApiConfig,
+    pub route_cache_config: RouteCacheConfig,
+    pub poi_cache_config: PoiCacheConfig,
+    pub poi_client_config: PoiClientConfig,
+    pub planner_config: PlannerConfig,
+    pub engine_config: EngineConfig,
 }
 
 impl Config {
@@ -164,8 +465,34 @@ impl Config {
     pub fn poi_server(&self) -> String {
         self.poi_server.clone()
     }
+
+    pub fn api(&self) -> ApiConfig {
+        self.api_config.clone()
+    }
+
+    pub fn route_cache(&self) -> RouteCacheConfig {
+        self.route_cache_config.clone()
+    }
+
+    pub fn poi_cache(&self) -> PoiCacheConfig {
+        self.poi_cache_config.clone()
+    }
+
+    pub fn poi_client(&self) -> PoiClientConfig {
+        self.poi_client_config.clone()
+    }
+
+    pub fn planner(&self) -> PlannerConfig {
+        self.planner_config.clone()
+    }
+
+    pub fn engine(&self) -> EngineConfig {
+        self.engine_config.clone()
+    }
 }
 
+/// Defaults do not read `config.yaml`, API settings and Redis keep their
+/// own defaults
 impl Default for Config {
     fn default() -> Self {
         let host = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
@@ -173,7 +500,7 @@ impl Default for Config {
         let address = SocketAddr::new(host, port);
         let root_dir = current_dir().unwrap();
 
-        let mut ret = Self {
+        Self {
             host,
             port,
             address,
@@ -186,9 +513,13 @@ impl Default for Config {
             action: ServerType::FileServices,
             redis_config: Default::default(),
             poi_server: Default::default(),
-        };
-        ret.init_redis();
-        return ret;
+            api_config: Default::default(),
+            route_cache_config: Default::default(),
+            poi_cache_config: Default::default(),
+            poi_client_config: Default::default(),
+            planner_config: Default::default(),
+            engine_config: Default::default(),
+        }
     }
 }
 
@@ -256,6 +587,12 @@ impl TryFrom<Cli> for Config {
             action: ServerType::from(cli_arguments.server_type),
             redis_config: Default::default(),
             poi_server: Default::default(),
+            api_config: Default::default(),
+            route_cache_config: Default::default(),
+            poi_cache_config: Default::default(),
+            poi_client_config: Default::default(),
+            planner_config: Default::default(),
+            engine_config: Default::default(),
         };
         ret.init_redis();
         return Ok(ret);
@@ -291,6 +628,12 @@ impl TryFrom<ConfigFile> for Config {
             action: ServerType::FileServices,
             redis_config: Default::default(),
             poi_server: Default::default(),
+            api_config: Default::default(),
+            route_cache_config: Default::default(),
+            poi_cache_config: Default::default(),
+            poi_client_config: Default::default(),
+            planner_config: Default::default(),
+            engine_config: Default::default(),
         };
         ret.init_redis();
         return Ok(ret);
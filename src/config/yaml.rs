@@ -1,9 +1,9 @@
 use super::Config;
+use std::convert::TryFrom;
 use std::error::Error;
 use std::fmt::Display;
 use std::fs::File;
 use std::io::Read;
-use std::io::Seek;
 use std::option::Option;
 use yaml_rust::Yaml;
 
@@ -23,55 +23,211 @@ impl Display for YamlError {
   }
 }
 
+/// A key the configuration can't do without
+fn _required<T>(value: Option<T>) -> Result<T, YamlError> {
+  value.ok_or_else(|| YamlError {
+    code: 2,
+    reason: String::from("parse is failed."),
+  })
+}
+
+/// A count or a duration, `None` when the key is absent. Negative or too
+/// large values are errors rather than wrapping around
+fn _unsigned<T: TryFrom<i64>>(value: &Yaml, key: &str) -> Result<Option<T>, YamlError> {
+  match value.as_i64() {
+    Some(number) => T::try_from(number).map(Some).map_err(|_| YamlError {
+      code: 5,
+      reason: format!("{} is out of range: {}", key, number),
+    }),
+    None => Ok(None),
+  }
+}
+
 pub fn read_config_from_yaml(config: &mut Config) -> Result<(), YamlError> {
-  fn _parse_config(config: &mut Config, docs: &Vec<Yaml>) -> Option<()> {
+  fn _parse_config(config: &mut Config, docs: &Vec<Yaml>) -> Result<(), YamlError> {
     let doc = &docs[0];
 
-    let poi_server = doc["poiServer"].as_str()?;
+    let poi_server = _required(doc["poiServer"].as_str())?;
     // let server_port = doc["server"]["port"].as_i64()? as u16;
-    let redis_mode = doc["redis"]["mode"].as_str()?;
-    let password = if false == doc["redis"]["password"].is_badvalue() {
-      doc["redis"]["password"].as_str()?
+    let redis_mode = _required(doc["redis"]["mode"].as_str())?;
+    // `Password` is the key of the shipped file, `password` is still read
+    // for older files
+    let password = if false == doc["redis"]["Password"].is_badvalue() {
+      _required(doc["redis"]["Password"].as_str())?
+    } else if false == doc["redis"]["password"].is_badvalue() {
+      _required(doc["redis"]["password"].as_str())?
     } else {
       ""
     };
-    let hosts = doc["redis"]["hosts"].as_str()?;
-    let dial_timeout = doc["redis"]["Connect"]["DialTimeout"].as_i64()?;
-    let write_timeout = doc["redis"]["Connect"]["WriteTimeout"].as_i64()?;
-    let read_timeout = doc["redis"]["Connect"]["ReadTimeout"].as_i64()?;
+    let hosts = _required(doc["redis"]["hosts"].as_str())?;
+    let dial_timeout = _required(doc["redis"]["Connect"]["DialTimeout"].as_i64())?;
+    let write_timeout = _required(doc["redis"]["Connect"]["WriteTimeout"].as_i64())?;
+    let read_timeout = _required(doc["redis"]["Connect"]["ReadTimeout"].as_i64())?;
     config.redis_config.connect.dial_timeout = dial_timeout;
     config.redis_config.connect.read_timeout = read_timeout;
     config.redis_config.connect.write_timeout = write_timeout;
+    if let Some(min_retry_backoff) = doc["redis"]["Connect"]["MinRetryBackoff"].as_i64() {
+      config.redis_config.connect.min_retry_backoff = min_retry_backoff;
+    }
+    if let Some(max_retry_backoff) = doc["redis"]["Connect"]["MaxRetryBackoff"].as_i64() {
+      config.redis_config.connect.max_retry_backoff = max_retry_backoff;
+    }
     config.redis_config.hosts = String::from(hosts);
     config.redis_config.pass = String::from(password);
     config.redis_config.mode = String::from(redis_mode);
+    if let Some(master_name) = doc["redis"]["MasterName"].as_str() {
+      config.redis_config.master_name = String::from(master_name);
+    }
+    if let Some(sentinel_password) = doc["redis"]["SentinelPassword"].as_str() {
+      config.redis_config.sentinel_password = String::from(sentinel_password);
+    }
+    if let Some(username) = doc["redis"]["Username"].as_str() {
+      config.redis_config.username = String::from(username);
+    }
+    if let Some(db) = doc["redis"]["DB"].as_i64() {
+      config.redis_config.db = db;
+    }
+    if let Some(tls) = doc["redis"]["TLS"].as_bool() {
+      config.redis_config.tls = tls;
+    }
+    if let Some(tls_insecure) = doc["redis"]["TLSInsecure"].as_bool() {
+      config.redis_config.tls_insecure = tls_insecure;
+    }
+    if let Some(pool_size) = _unsigned(&doc["redis"]["Pool"]["PoolSize"], "redis.Pool.PoolSize")? {
+      config.redis_config.pool.pool_size = pool_size;
+    }
+    if let Some(min_idle_conns) = _unsigned(
+      &doc["redis"]["Pool"]["MinIdleConns"],
+      "redis.Pool.MinIdleConns",
+    )? {
+      config.redis_config.pool.min_idle_conns = min_idle_conns;
+    }
+    if let Some(max_conn_age) =
+      _unsigned(&doc["redis"]["Pool"]["MaxConnAge"], "redis.Pool.MaxConnAge")?
+    {
+      config.redis_config.pool.max_conn_age = max_conn_age;
+    }
     config.poi_server = String::from(poi_server);
+    if let Some(max_body_size) = _unsigned(&doc["server"]["maxBodySize"], "server.maxBodySize")? {
+      config.api_config.max_body_size = max_body_size;
+    }
+    if let Some(workers) = _unsigned(&doc["planner"]["workers"], "planner.workers")? {
+      config.planner_config.workers = workers;
+    }
+    if let Some(queue_depth) = _unsigned(&doc["planner"]["queueDepth"], "planner.queueDepth")? {
+      config.planner_config.queue_depth = queue_depth;
+    }
+    if let Some(timeout) = _unsigned(&doc["planner"]["timeout"], "planner.timeout")? {
+      config.planner_config.timeout = timeout;
+    }
+    if let Some(retry_after) = _unsigned(&doc["planner"]["retryAfter"], "planner.retryAfter")? {
+      config.planner_config.retry_after = retry_after;
+    }
+    if let Some(kind) = doc["engine"]["kind"].as_str() {
+      config.engine_config.kind = String::from(kind);
+    }
+    if let Some(graph) = doc["engine"]["graph"].as_str() {
+      config.engine_config.graph = String::from(graph);
+    }
+    if let Some(walk_mode) = _unsigned(&doc["engine"]["walkMode"], "engine.walkMode")? {
+      config.engine_config.walk_mode = walk_mode;
+    }
+    if let Some(policy) = _unsigned(
+      &doc["engine"]["fewestTurnsPolicy"],
+      "engine.fewestTurnsPolicy",
+    )? {
+      config.engine_config.fewest_turns_policy = policy;
+    }
+    let poi_client = &doc["poiClient"];
+    if let Some(connect_timeout) =
+      _unsigned(&poi_client["connectTimeout"], "poiClient.connectTimeout")?
+    {
+      config.poi_client_config.connect_timeout = connect_timeout;
+    }
+    if let Some(request_timeout) =
+      _unsigned(&poi_client["requestTimeout"], "poiClient.requestTimeout")?
+    {
+      config.poi_client_config.request_timeout = request_timeout;
+    }
+    if let Some(pool_idle_timeout) =
+      _unsigned(&poi_client["poolIdleTimeout"], "poiClient.poolIdleTimeout")?
+    {
+      config.poi_client_config.pool_idle_timeout = pool_idle_timeout;
+    }
+    if let Some(pool_max_idle_per_host) = _unsigned(
+      &poi_client["poolMaxIdlePerHost"],
+      "poiClient.poolMaxIdlePerHost",
+    )? {
+      config.poi_client_config.pool_max_idle_per_host = pool_max_idle_per_host;
+    }
+    if let Some(retries) = _unsigned(&poi_client["retries"], "poiClient.retries")? {
+      config.poi_client_config.retries = retries;
+    }
+    if let Some(retry_backoff) = _unsigned(&poi_client["retryBackoff"], "poiClient.retryBackoff")? {
+      config.poi_client_config.retry_backoff = retry_backoff;
+    }
+    if let Some(max_retry_backoff) =
+      _unsigned(&poi_client["maxRetryBackoff"], "poiClient.maxRetryBackoff")?
+    {
+      config.poi_client_config.max_retry_backoff = max_retry_backoff;
+    }
+    if let Some(breaker_threshold) = _unsigned(
+      &poi_client["breakerThreshold"],
+      "poiClient.breakerThreshold",
+    )? {
+      config.poi_client_config.breaker_threshold = breaker_threshold;
+    }
+    if let Some(breaker_cooldown) =
+      _unsigned(&poi_client["breakerCooldown"], "poiClient.breakerCooldown")?
+    {
+      config.poi_client_config.breaker_cooldown = breaker_cooldown;
+    }
+    if let Some(backend) = doc["poiCache"]["backend"].as_str() {
+      config.poi_cache_config.backend = String::from(backend);
+    }
+    if let Some(max_entries) = _unsigned(&doc["poiCache"]["maxEntries"], "poiCache.maxEntries")? {
+      config.poi_cache_config.max_entries = max_entries;
+    }
+    if let Some(ttl) = _unsigned(&doc["poiCache"]["ttl"], "poiCache.ttl")? {
+      config.poi_cache_config.ttl = ttl;
+    }
+    if let Some(negative_ttl) = _unsigned(&doc["poiCache"]["negativeTTL"], "poiCache.negativeTTL")?
+    {
+      config.poi_cache_config.negative_ttl = negative_ttl;
+    }
+    if let Some(backend) = doc["routeCache"]["backend"].as_str() {
+      config.route_cache_config.backend = String::from(backend);
+    }
+    if let Some(max_entries) = _unsigned(&doc["routeCache"]["maxEntries"], "routeCache.maxEntries")?
+    {
+      config.route_cache_config.max_entries = max_entries;
+    }
+    if let Some(max_bytes) = _unsigned(&doc["routeCache"]["maxBytes"], "routeCache.maxBytes")? {
+      config.route_cache_config.max_bytes = max_bytes;
+    }
+    if let Some(route_ttl) = _unsigned(&doc["routeCache"]["routeTTL"], "routeCache.routeTTL")? {
+      config.route_cache_config.route_ttl = route_ttl;
+    }
+    if let Some(plan_ttl) = _unsigned(&doc["routeCache"]["planTTL"], "routeCache.planTTL")? {
+      config.route_cache_config.plan_ttl = plan_ttl;
+    }
     // config.port = server_port;
-    Some(())
+    Ok(())
   }
   fn _read_file() -> Result<String, Box<dyn Error>> {
     let mut file = File::open("config.yaml")?;
     let method_type = 3;
     if 1 == method_type {
-      let before = file.stream_position()?;
       let content = &mut [0; 1024];
       let size = file.read(&mut content[..])?;
-      let after = file.stream_position()?;
-      println!("ret={}:{}:{}:{:?}", before, after, size, &content);
-      Ok(String::from_utf8(content.to_vec())?)
+      Ok(String::from_utf8(content[..size].to_vec())?)
     } else if 2 == method_type {
-      let before = file.stream_position()?;
       let mut bytes = Vec::with_capacity(100);
       file.read_to_end(&mut bytes)?;
-      let after = file.stream_position()?;
-      println!("ret={}:{}:{:?}", before, after, &bytes);
       Ok(String::from_utf8(bytes)?)
     } else if 3 == method_type {
-      let before = file.stream_position()?;
       let mut buf = String::new();
-      let size = file.read_to_string(&mut buf)?;
-      let after = file.stream_position()?;
-      println!("ret={}:{}:{}:{:?}", before, after, size, &buf);
+      file.read_to_string(&mut buf)?;
       Ok(buf)
     } else {
       Err(Box::new(YamlError {
@@ -87,16 +243,7 @@ pub fn read_config_from_yaml(config: &mut Config) -> Result<(), YamlError> {
       match ret {
         Ok(docs) => {
           let mut config = config;
-          match _parse_config(&mut config, &docs) {
-            Some(_) => {}
-            None => {
-              return Err(YamlError {
-                code: 2,
-                reason: String::from("parse is failed."),
-              });
-            }
-          }
-          return Ok(());
+          return _parse_config(&mut config, &docs);
         }
         Err(error) => {
           println!("{:?}", error);
@@ -128,6 +275,21 @@ mod test {
     println!("test_yaml>ret={:?}", read_config_from_yaml(&mut config));
   }
   #[test]
+  fn rejects_out_of_range_numbers() {
+    let out = YamlLoader::load_from_str("poolSize: -1\nttl: 600\nretries: 5000000000").unwrap();
+    let doc = &out[0];
+
+    assert_eq!(_unsigned::<u64>(&doc["ttl"], "ttl").unwrap(), Some(600));
+    assert_eq!(_unsigned::<u64>(&doc["missing"], "missing").unwrap(), None);
+    assert_eq!(
+      _unsigned::<usize>(&doc["poolSize"], "redis.Pool.PoolSize")
+        .unwrap_err()
+        .to_string(),
+      "yaml error: 5:\"redis.Pool.PoolSize is out of range: -1\""
+    );
+    assert!(_unsigned::<u32>(&doc["retries"], "poiClient.retries").is_err());
+  }
+  #[test]
   fn test_anchor() {
     let s = "
 a1: &DEFAULT
@@ -28,7 +28,7 @@ fn resolve_config(cli_arguments: cli::Cli) -> Result<Config> {
 pub fn make_server() -> Result<Server> {
     let cli_arguments = cli::Cli::from_args();
     let config = resolve_config(cli_arguments)?;
-    let server = Server::new(config);
+    let server = Server::new(config)?;
 
     Ok(server)
 }
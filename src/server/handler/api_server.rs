@@ -1,5 +1,3 @@
-use http::response::Builder as HttpResponseBuilder;
-use http::StatusCode;
 use hyper::{Body, Request};
 use std::sync::Arc;
 use tokio::sync::Mutex;
@@ -30,30 +28,9 @@ impl ServerHandler for ApiServerHandler {
 
     Box::new(move |request: Arc<Mutex<Request<Body>>>| {
       let api_server = Arc::clone(&api_server);
-      
+
       Box::pin(async move {
-        match api_server.resolve(request).await {
-          Ok(ret) => ret,
-          Err(e) => {
-            let ret = HttpResponseBuilder::new()
-              .status(StatusCode::NOT_FOUND)
-              .header(http::header::CONTENT_TYPE, "text/html")
-              .body(Body::from(e.to_string()))
-              .expect("Unable to build response");
-            println!("{:?}", ret);
-            return ret;
-          }
-        }
-        // api_server
-        //   .resolve(request)
-        //   .await
-        //   .map_err(|e| {
-        //     HttpResponseBuilder::new()
-        //       .status(e)
-        //       .body(Body::from(e.to_string()))
-        //       .expect("Unable to build response")
-        //   })
-        //   .unwrap()
+        api_server.resolve(request).await
       })
     })
   }
@@ -1,7 +1,7 @@
 mod api_server;
 mod file_server;
 
-use anyhow::Result;
+use anyhow::{Error, Result};
 use futures::Future;
 use http::{Request, Response};
 use hyper::Body;
@@ -49,28 +49,28 @@ pub struct HttpHandler {
 }
 
 impl HttpHandler {
-    fn new(config: Arc<Config>) -> Self {
+    fn new(config: Arc<Config>) -> Result<Self> {
         let middleware = Middleware::try_from(Arc::clone(&config)).unwrap();
         let middleware = Arc::new(middleware);
         match config.action() {
             ServerType::ApiServices => {
-                let api_server = ApiServer::new(Arc::clone(&config));
-                HttpHandler {
+                let api_server = ApiServer::new(Arc::clone(&config)).map_err(Error::msg)?;
+                Ok(HttpHandler {
                     middleware: middleware,
                     file_server_handler: Default::default(),
                     api_server_handler: Arc::new(ApiServerHandler::new_api(api_server)),
                     server_type: config.action(),
-                }
+                })
             }
             ServerType::FileServices => {
                 let file_server = FileServer::new(config.root_dir());
                 let file_server_handler = Arc::new(FileServerHandler::new_file(file_server));
-                HttpHandler {
+                Ok(HttpHandler {
                     middleware: middleware,
                     file_server_handler: file_server_handler,
                     api_server_handler: Default::default(),
                     server_type: config.action(),
-                }
+                })
             }
             ServerType::UnknownServices => panic!("not support this type"),
         }
@@ -94,9 +94,10 @@ impl HttpHandler {
     }
 }
 
-impl From<Arc<Config>> for HttpHandler {
-    fn from(config: Arc<Config>) -> Self {
-        // HttpHandler::new(Arc::clone(&config))
+impl TryFrom<Arc<Config>> for HttpHandler {
+    type Error = Error;
+
+    fn try_from(config: Arc<Config>) -> Result<Self, Self::Error> {
         HttpHandler::new(config)
     }
 }
@@ -6,6 +6,7 @@ pub mod middleware;
 
 use anyhow::Error;
 use hyper::service::{make_service_fn, service_fn};
+use std::convert::TryFrom;
 use std::net::{Ipv4Addr, SocketAddr};
 use std::str::FromStr;
 use std::sync::Arc;
@@ -15,21 +16,25 @@ use crate::config::Config;
 
 pub struct Server {
     config: Arc<Config>,
+    handler: handler::HttpHandler,
 }
 
 impl Server {
-    pub fn new(config: Config) -> Server {
+    /// Fails when the handler of the configured server type can't be
+    /// created
+    pub fn new(config: Config) -> Result<Server, Error> {
         let config = Arc::new(config);
         if config.verbose() {
             println!("config={:?}", config);
         }
-        Server { config }        
+        let handler = handler::HttpHandler::try_from(Arc::clone(&config))?;
+        Ok(Server { config, handler })
     }
 
     pub async fn run(self) {
         let config = Arc::clone(&self.config);
         let address = config.address();
-        let handler = handler::HttpHandler::from(Arc::clone(&config));
+        let handler = self.handler.clone();
         let server = Arc::new(self);
         let mut server_instances: Vec<tokio::task::JoinHandle<()>> = Vec::new();
 
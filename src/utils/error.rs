@@ -2,10 +2,18 @@ use http::{Response, StatusCode};
 use hyper::Body;
 use serde::Serialize;
 
+use super::trace::TRACE_ID_HEADER;
+
 #[derive(Debug, Serialize)]
 struct ErrorResponseBody {
     status_code: u16,
     message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trace_id: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<String>,
 }
 
 pub fn make_http_error_response(status: StatusCode, message: &str) -> Response<Body> {
@@ -15,6 +23,38 @@ pub fn make_http_error_response(status: StatusCode, message: &str) -> Response<B
             serde_json::ser::to_string(&ErrorResponseBody {
                 status_code: status.as_u16(),
                 message: message.to_string(),
+                code: None,
+                trace_id: None,
+                errors: Vec::new(),
+            })
+            .unwrap(),
+        ))
+        .unwrap()
+}
+
+/// Creates a JSON error response with the same shape as
+/// `make_http_error_response` adding a machine-readable `code` and the
+/// `trace_id` of the request, which is also sent as the `X-Trace-Id` header.
+///
+/// When not empty, `errors` lists every problem found on the request
+pub fn make_http_error_response_with_code(
+    status: StatusCode,
+    code: &str,
+    message: &str,
+    trace_id: &str,
+    errors: Vec<String>,
+) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .header(TRACE_ID_HEADER, trace_id)
+        .body(Body::from(
+            serde_json::ser::to_string(&ErrorResponseBody {
+                status_code: status.as_u16(),
+                message: message.to_string(),
+                code: Some(code.to_string()),
+                trace_id: Some(trace_id.to_string()),
+                errors,
             })
             .unwrap(),
         ))
@@ -1,3 +1,4 @@
 pub mod error;
 pub mod fmt;
 pub mod signal;
+pub mod trace;
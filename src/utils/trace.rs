@@ -0,0 +1,80 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// HTTP header used to receive and send the trace id of a request
+pub const TRACE_ID_HEADER: &str = "x-trace-id";
+
+/// HTTP header listing the trace ids given by upstream services while
+/// serving a request
+pub const UPSTREAM_TRACE_ID_HEADER: &str = "x-upstream-trace-id";
+
+static TRACE_ID_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// Creates a new trace id from the current time and a process wide
+/// sequence, which is unique for every call on the same process
+pub fn make_trace_id() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos() as u64)
+        .unwrap_or_default();
+    let sequence = TRACE_ID_SEQUENCE.fetch_add(1, Ordering::Relaxed);
+
+    format!("{:016x}{:08x}", nanos, sequence as u32)
+}
+
+/// Trace ids of the upstream services called while serving a request, in
+/// call order and without duplicates
+#[derive(Debug, Default)]
+pub struct UpstreamTraceIds {
+    ids: Mutex<Vec<String>>,
+}
+
+impl UpstreamTraceIds {
+    pub fn push(&self, trace_id: &str) {
+        if trace_id.is_empty() {
+            return;
+        }
+        let mut ids = self.ids.lock().expect("upstream trace ids");
+        if !ids.iter().any(|id| id == trace_id) {
+            ids.push(String::from(trace_id));
+        }
+    }
+
+    /// The value of the `X-Upstream-Trace-Id` header, `None` when no
+    /// upstream service gave a trace id
+    pub fn header_value(&self) -> Option<String> {
+        let ids = self.ids.lock().expect("upstream trace ids");
+        if ids.is_empty() {
+            None
+        } else {
+            Some(ids.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn makes_unique_trace_ids() {
+        let first = make_trace_id();
+        let second = make_trace_id();
+
+        assert_eq!(first.len(), 24);
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn joins_upstream_trace_ids() {
+        let upstream = UpstreamTraceIds::default();
+        assert_eq!(upstream.header_value(), None);
+
+        upstream.push("a1");
+        upstream.push("");
+        upstream.push("b2");
+        upstream.push("a1");
+        assert_eq!(upstream.header_value(), Some(String::from("a1, b2")));
+    }
+}